@@ -0,0 +1,373 @@
+//! static [activitypub](https://www.w3.org/TR/activitypub/) documents, so that fediverse software
+//! can look up `[self_author]` and read the threads in the index collection:
+//!
+//! - `.well-known/webfinger` maps `acct:<name>@<host>` to the actor
+//! - `activitypub/actor.json` is a `Person` for `[self_author]`
+//! - `activitypub/outbox.json` is an `OrderedCollection` of activities, newest first, split into
+//!   `activitypub/outbox.<page>.json`
+//! - `activitypub/inbox.json` is always empty, because a static site can’t accept activities
+//!
+//! transparent shares become `Announce` activities for the shared post, and other threads become
+//! `Create` activities for a `Note`, or an `Article` if the thread has a title. objects use the
+//! same permalinks as the atom feed, since their ids need to be absolute urls.
+
+use jane_eyre::eyre::{self, OptionExt};
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    output::absolutise_relative_urls_in_html_fragment,
+    path::{SitePath, SITE_PATH_ACTIVITYPUB, SITE_PATH_TAGGED, SITE_PATH_WELL_KNOWN},
+    Author, TemplatedPost, Thread, SETTINGS,
+};
+
+/// maximum number of activities in each `activitypub/outbox.<page>.json`.
+pub const OUTBOX_PAGE_SIZE: usize = 50;
+
+const CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+#[derive(Debug, Serialize)]
+struct WebFinger {
+    subject: String,
+    aliases: Vec<String>,
+    links: Vec<WebFingerLink>,
+}
+
+#[derive(Debug, Serialize)]
+struct WebFingerLink {
+    rel: &'static str,
+    r#type: &'static str,
+    href: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Actor<'author> {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    id: String,
+    r#type: &'static str,
+    preferred_username: &'author str,
+    name: &'author str,
+    url: &'author str,
+    inbox: String,
+    outbox: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderedCollection {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    id: String,
+    r#type: &'static str,
+    total_items: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ordered_items: Option<Vec<Activity>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderedCollectionPage {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    id: String,
+    r#type: &'static str,
+    part_of: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prev: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+    ordered_items: Vec<Activity>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Activity {
+    id: String,
+    r#type: &'static str,
+    actor: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+    to: [&'static str; 1],
+    object: ActivityObject,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum ActivityObject {
+    Url(String),
+    Object(Box<Object>),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Object {
+    id: String,
+    r#type: &'static str,
+    attributed_to: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    content: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    in_reply_to: Option<String>,
+    to: [&'static str; 1],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tag: Vec<Hashtag>,
+}
+
+#[derive(Debug, Serialize)]
+struct Hashtag {
+    r#type: &'static str,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    href: Option<String>,
+}
+
+pub fn webfinger_path() -> eyre::Result<SitePath> {
+    SITE_PATH_WELL_KNOWN.join("webfinger")
+}
+
+pub fn actor_path() -> eyre::Result<SitePath> {
+    SITE_PATH_ACTIVITYPUB.join("actor.json")
+}
+
+pub fn inbox_path() -> eyre::Result<SitePath> {
+    SITE_PATH_ACTIVITYPUB.join("inbox.json")
+}
+
+pub fn outbox_path() -> eyre::Result<SitePath> {
+    SITE_PATH_ACTIVITYPUB.join("outbox.json")
+}
+
+/// `page` is 1-based.
+pub fn outbox_page_path(page: usize) -> eyre::Result<SitePath> {
+    SITE_PATH_ACTIVITYPUB.join(&format!("outbox.{page}.json"))
+}
+
+/// the handle that fediverse software can look up with webfinger, like `eggbug@example.com`.
+pub fn handle(author: &Author) -> eyre::Result<String> {
+    let external_base_url = Url::parse(&SETTINGS.external_base_url)?;
+    let host = external_base_url
+        .host_str()
+        .ok_or_eyre("external_base_url has no host")?;
+
+    Ok(format!("{}@{host}", author.name))
+}
+
+pub fn render_webfinger(author: &Author) -> eyre::Result<String> {
+    let actor_url = actor_path()?.external_url();
+
+    Ok(serde_json::to_string_pretty(&WebFinger {
+        subject: format!("acct:{}", handle(author)?),
+        aliases: vec![actor_url.clone(), author.href.clone()],
+        links: vec![
+            WebFingerLink {
+                rel: "self",
+                r#type: "application/activity+json",
+                href: actor_url,
+            },
+            WebFingerLink {
+                rel: "http://webfinger.net/rel/profile-page",
+                r#type: "text/html",
+                href: author.href.clone(),
+            },
+        ],
+    })?)
+}
+
+pub fn render_actor(author: &Author) -> eyre::Result<String> {
+    Ok(serde_json::to_string_pretty(&Actor {
+        context: CONTEXT,
+        id: actor_path()?.external_url(),
+        r#type: "Person",
+        preferred_username: &author.name,
+        name: &author.display_name,
+        url: &author.href,
+        inbox: inbox_path()?.external_url(),
+        outbox: outbox_path()?.external_url(),
+    })?)
+}
+
+pub fn render_inbox() -> eyre::Result<String> {
+    Ok(serde_json::to_string_pretty(&OrderedCollection {
+        context: CONTEXT,
+        id: inbox_path()?.external_url(),
+        r#type: "OrderedCollection",
+        total_items: 0,
+        first: None,
+        last: None,
+        ordered_items: Some(vec![]),
+    })?)
+}
+
+pub fn render_outbox(total_items: usize, page_count: usize) -> eyre::Result<String> {
+    let first = (page_count > 0)
+        .then(|| outbox_page_path(1))
+        .transpose()?
+        .map(|path| path.external_url());
+    let last = (page_count > 0)
+        .then(|| outbox_page_path(page_count))
+        .transpose()?
+        .map(|path| path.external_url());
+
+    Ok(serde_json::to_string_pretty(&OrderedCollection {
+        context: CONTEXT,
+        id: outbox_path()?.external_url(),
+        r#type: "OrderedCollection",
+        total_items,
+        first,
+        last,
+        ordered_items: None,
+    })?)
+}
+
+/// `page` is 1-based, and `threads` are newest first.
+pub fn render_outbox_page<'thread>(
+    threads: impl Iterator<Item = &'thread Thread>,
+    page: usize,
+    page_count: usize,
+) -> eyre::Result<String> {
+    let ordered_items = threads
+        .map(thread_activity)
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let prev = (page > 1)
+        .then(|| outbox_page_path(page - 1))
+        .transpose()?
+        .map(|path| path.external_url());
+    let next = (page < page_count)
+        .then(|| outbox_page_path(page + 1))
+        .transpose()?
+        .map(|path| path.external_url());
+
+    Ok(serde_json::to_string_pretty(&OrderedCollectionPage {
+        context: CONTEXT,
+        id: outbox_page_path(page)?.external_url(),
+        r#type: "OrderedCollectionPage",
+        part_of: outbox_path()?.external_url(),
+        prev,
+        next,
+        ordered_items,
+    })?)
+}
+
+fn thread_activity(thread: &Thread) -> eyre::Result<Activity> {
+    let permalink = thread
+        .url_for_atom_permalink()?
+        .ok_or_eyre("thread has no permalink")?;
+    let actor = actor_path()?.external_url();
+    let post = thread.main_post()?;
+    let earlier_posts = &thread.posts[..thread.posts.len() - 1];
+
+    if post.meta.is_transparent_share {
+        let shared_post = earlier_posts
+            .iter()
+            .rev()
+            .find(|post| !post.meta.is_transparent_share);
+        if let Some(shared_url) = shared_post.map(post_url).transpose()?.flatten() {
+            return Ok(Activity {
+                id: permalink,
+                r#type: "Announce",
+                actor,
+                published: thread.meta.published.clone(),
+                to: [PUBLIC],
+                object: ActivityObject::Url(shared_url),
+            });
+        }
+    }
+
+    let in_reply_to = earlier_posts.last().map(post_url).transpose()?.flatten();
+    let tag = post
+        .meta
+        .tags
+        .iter()
+        .map(|tag| {
+            let href = SETTINGS
+                .tag_has_page(tag)
+                .then(|| SITE_PATH_TAGGED.join(&format!("{tag}.html")))
+                .transpose()?
+                .map(|path| path.external_url());
+            Ok(Hashtag {
+                r#type: "Hashtag",
+                name: format!("#{tag}"),
+                href,
+            })
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    Ok(Activity {
+        id: format!("{permalink}#create"),
+        r#type: "Create",
+        actor: actor.clone(),
+        published: thread.meta.published.clone(),
+        to: [PUBLIC],
+        object: ActivityObject::Object(Box::new(Object {
+            id: permalink.clone(),
+            r#type: if thread.meta.title.is_some() {
+                "Article"
+            } else {
+                "Note"
+            },
+            attributed_to: actor,
+            name: thread.meta.title.clone(),
+            content: absolutise_relative_urls_in_html_fragment(&post.safe_html)?,
+            url: permalink,
+            published: thread.meta.published.clone(),
+            updated: thread.meta.updated.clone(),
+            in_reply_to,
+            to: [PUBLIC],
+            tag,
+        })),
+    })
+}
+
+/// the url of a post in a thread: the original url if it was imported, or else its own page.
+fn post_url(post: &TemplatedPost) -> eyre::Result<Option<String>> {
+    if let Some(archived) = &post.meta.archived {
+        return Ok(Some(archived.clone()));
+    }
+    let result = post
+        .path
+        .as_ref()
+        .map(|path| path.rendered_path())
+        .transpose()?
+        .flatten()
+        .map(|path| path.external_url());
+
+    Ok(result)
+}
+
+#[test]
+fn test_render_webfinger() -> eyre::Result<()> {
+    let author = SETTINGS.self_author.as_ref().ok_or_eyre("no self_author")?;
+    let webfinger: serde_json::Value = serde_json::from_str(&render_webfinger(author)?)?;
+    assert_eq!(webfinger["subject"], "acct:eggbug@example.com");
+    assert_eq!(
+        webfinger["links"][0]["href"],
+        "https://example.com/activitypub/actor.json"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_absolutise_relative_urls_in_html_fragment() -> eyre::Result<()> {
+    assert_eq!(
+        absolutise_relative_urls_in_html_fragment(
+            r#"<img src="attachments/a.png"><a href="/x">x</a><a href="https://example.net/">y</a>"#
+        )?,
+        r#"<img src="https://example.com/attachments/a.png"><a href="/x">x</a><a href="https://example.net/">y</a>"#
+    );
+
+    Ok(())
+}
@@ -1,5 +1,8 @@
+//! types for the mastodon client api, which akkoma, gotosocial, pleroma, and others also serve.
+
 use askama::Template;
 use serde::Deserialize;
+use url::Url;
 
 use crate::Author;
 
@@ -13,12 +16,30 @@ pub struct ApiInstance {
 /// <https://docs.joinmastodon.org/entities/Status/>
 #[derive(Deserialize)]
 pub struct ApiStatus {
+    pub id: String,
+    pub uri: String,
+    /// the html page of the status, which mastodon may leave out for some remote statuses.
+    pub url: Option<String>,
     pub content: String,
-    pub url: String,
+    /// content warning, or empty if none.
+    #[serde(default)]
+    pub spoiler_text: String,
+    /// whether the media attachments should be hidden by default.
+    #[serde(default)]
+    pub sensitive: bool,
+    pub in_reply_to_id: Option<String>,
     pub account: ApiAccount,
     pub media_attachments: Vec<ApiMediaAttachment>,
     pub tags: Vec<ApiStatusTag>,
     pub created_at: String,
+    pub edited_at: Option<String>,
+}
+
+/// <https://docs.joinmastodon.org/entities/Context/>
+#[derive(Deserialize)]
+pub struct ApiContext {
+    /// oldest first.
+    pub ancestors: Vec<ApiStatus>,
 }
 
 /// <https://docs.joinmastodon.org/entities/Status/#Tag>
@@ -34,7 +55,7 @@ pub struct ApiMediaAttachment {
     pub r#type: String,
     pub description: Option<String>,
     pub url: String,
-    pub preview_url: String,
+    pub preview_url: Option<String>,
 }
 
 /// <https://docs.joinmastodon.org/entities/Account/>
@@ -44,6 +65,8 @@ pub struct ApiAccount {
     pub display_name: String,
     pub username: String,
     pub acct: String,
+    /// akkoma and pleroma only; see [`ApiAccount::fqn`].
+    #[serde(default)]
     pub fqn: String,
 }
 
@@ -56,17 +79,70 @@ pub struct AkkomaImgTemplate {
     pub alt: Option<String>,
 }
 
+/// `video` and `gifv` attachments. gifv attachments are silent videos that play by themselves and
+/// loop, like gifs.
+#[derive(Template)]
+#[template(path = "akkoma-video.html")]
+pub struct AkkomaVideoTemplate {
+    pub data_akkoma_src: String,
+    pub src: String,
+    pub description: Option<String>,
+    pub is_gifv: bool,
+}
+
+#[derive(Template)]
+#[template(path = "akkoma-audio.html")]
+pub struct AkkomaAudioTemplate {
+    pub data_akkoma_src: String,
+    pub src: String,
+    pub description: Option<String>,
+}
+
+/// attachments of `unknown` type, which we link to rather than download.
+#[derive(Template)]
+#[template(path = "akkoma-attachment.html")]
+pub struct AkkomaAttachmentTemplate {
+    pub href: String,
+    pub description: Option<String>,
+}
+
+/// `<details>` for content warnings and sensitive media.
+#[derive(Template)]
+#[template(path = "content-warning.html")]
+pub struct ContentWarningTemplate {
+    pub summary: String,
+    pub content: String,
+}
+
+impl ApiAccount {
+    /// the fully qualified `user@host` of the account. `acct` has no host for local accounts, so
+    /// other servers need the host from the account url.
+    pub fn fqn(&self) -> String {
+        if !self.fqn.is_empty() {
+            return self.fqn.clone();
+        }
+        if self.acct.contains('@') {
+            return self.acct.clone();
+        }
+        match Url::parse(&self.url).ok().as_ref().and_then(Url::host_str) {
+            Some(host) => format!("{}@{host}", self.username),
+            None => self.acct.clone(),
+        }
+    }
+}
+
 impl From<&ApiAccount> for Author {
     fn from(account: &ApiAccount) -> Self {
+        let fqn = account.fqn();
         Self {
             href: account.url.clone(),
             name: if account.display_name.is_empty() {
-                format!("@{}", account.fqn)
+                format!("@{fqn}")
             } else {
-                format!("{} (@{})", account.display_name, account.fqn)
+                format!("{} (@{fqn})", account.display_name)
             },
             display_name: account.display_name.clone(),
-            display_handle: format!("@{}", account.fqn),
+            display_handle: format!("@{fqn}"),
         }
     }
 }
@@ -105,4 +181,21 @@ fn test_author_from_api_account() {
             display_handle: "@ruby@posting.isincredibly.gay".to_owned(),
         }
     );
+
+    // mastodon has no fqn, and no host in acct for local accounts
+    assert_eq!(
+        Author::from(&ApiAccount {
+            url: "https://mastodon.example/@eggbug".to_owned(),
+            display_name: "eggbug".to_owned(),
+            username: "eggbug".to_owned(),
+            acct: "eggbug".to_owned(),
+            fqn: "".to_owned(),
+        }),
+        Author {
+            href: "https://mastodon.example/@eggbug".to_owned(),
+            name: "eggbug (@eggbug@mastodon.example)".to_owned(),
+            display_name: "eggbug".to_owned(),
+            display_handle: "@eggbug@mastodon.example".to_owned(),
+        }
+    );
 }
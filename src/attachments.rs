@@ -10,6 +10,7 @@ use jane_eyre::eyre::{self, bail, OptionExt};
 use reqwest::{redirect::Policy, StatusCode};
 use sha2::{digest::generic_array::functional::FunctionalSequence, Digest, Sha256};
 use tracing::{debug, error, trace, warn};
+use url::Url;
 use uuid::Uuid;
 
 use crate::{
@@ -185,16 +186,17 @@ fn cache_imported_attachment(url: &str, path: &AttachmentsPath) -> eyre::Result<
     debug!("downloading attachment");
 
     let response = reqwest::blocking::get(url)?;
-    let extension = match response.headers().get("Content-Type") {
-        Some(x) if x == "image/gif" => "gif",
-        Some(x) if x == "image/jpeg" => "jpg",
-        Some(x) if x == "image/png" => "png",
-        Some(x) if x == "image/svg+xml" => "svg",
-        Some(x) if x == "image/webp" => "webp",
-        other => {
-            warn!("unknown attachment mime type: {other:?}");
-            "bin"
-        }
+    let content_type = response.headers().get("Content-Type");
+    let extension = match content_type
+        .and_then(|x| x.to_str().ok())
+        .and_then(mime_type_extension)
+    {
+        Some(extension) => extension.to_owned(),
+        // some servers send `application/octet-stream` for everything, so try the url.
+        None => url_extension(url).unwrap_or_else(|| {
+            warn!("unknown attachment mime type: {content_type:?}");
+            "bin".to_owned()
+        }),
     };
     let path = path.join(&format!("file.{extension}"))?;
     debug!(?path);
@@ -205,6 +207,48 @@ fn cache_imported_attachment(url: &str, path: &AttachmentsPath) -> eyre::Result<
     Ok(path)
 }
 
+/// the file extension for an attachment with the given `Content-Type`, ignoring any parameters.
+fn mime_type_extension(content_type: &str) -> Option<&'static str> {
+    let mime_type = content_type.split(';').next()?.trim().to_ascii_lowercase();
+
+    Some(match &*mime_type {
+        "image/avif" => "avif",
+        "image/gif" => "gif",
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/svg+xml" => "svg",
+        "image/webp" => "webp",
+        "video/mp4" => "mp4",
+        "video/ogg" => "ogv",
+        "video/quicktime" => "mov",
+        "video/webm" => "webm",
+        "audio/aac" => "aac",
+        "audio/flac" | "audio/x-flac" => "flac",
+        "audio/mp4" | "audio/x-m4a" => "m4a",
+        "audio/mpeg" => "mp3",
+        "audio/ogg" => "ogg",
+        "audio/opus" => "opus",
+        "audio/wav" | "audio/wave" | "audio/x-wav" => "wav",
+        "audio/webm" => "weba",
+        _ => return None,
+    })
+}
+
+/// the file extension in the last path segment of the url, if it looks like one.
+fn url_extension(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let filename = url.path_segments()?.next_back()?;
+    let (_, extension) = filename.rsplit_once('.')?;
+    if extension.is_empty()
+        || extension.len() > 5
+        || !extension.bytes().all(|x| x.is_ascii_alphanumeric())
+    {
+        return None;
+    }
+
+    Some(extension.to_ascii_lowercase())
+}
+
 /// given a cohost attachment redirect (`url`) and path to a uuid dir (`path`),
 /// return the cached attachment path (`path/original-filename.ext`).
 ///
@@ -344,3 +388,20 @@ fn cache_other_cohost_resource(url: &str, path: &AttachmentsPath) -> eyre::Resul
 
     Ok(path.clone())
 }
+
+#[test]
+fn test_imported_attachment_extension() {
+    assert_eq!(mime_type_extension("image/png"), Some("png"));
+    assert_eq!(mime_type_extension("video/mp4"), Some("mp4"));
+    assert_eq!(mime_type_extension("Audio/Ogg; codecs=opus"), Some("ogg"));
+    assert_eq!(mime_type_extension("application/octet-stream"), None);
+    assert_eq!(
+        url_extension("https://example.net/media/a.b/Clip.MP4?x=y"),
+        Some("mp4".to_owned())
+    );
+    assert_eq!(url_extension("https://example.net/media/clip"), None);
+    assert_eq!(
+        url_extension("https://example.net/media/clip.tar%2Fgz"),
+        None
+    );
+}
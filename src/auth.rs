@@ -0,0 +1,192 @@
+//! login sessions and csrf protection for `autost server`, when `server_password_hash` is set.
+//!
+//! sessions only live in memory, so restarting the server logs everyone out. sessions also expire
+//! after [`SESSION_LIFETIME`].
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier as _};
+use jane_eyre::eyre::{self, eyre};
+use rocket::{
+    http::Status,
+    request::{FromRequest, Outcome},
+    tokio::task::spawn_blocking,
+    Request,
+};
+use uuid::Uuid;
+
+use crate::{
+    rocket_eyre::{self, EyreReport},
+    SETTINGS,
+};
+
+pub const SESSION_COOKIE: &str = "autost_session";
+
+/// how long you stay logged in, before you need to log in again.
+pub const SESSION_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+    /// session id → session.
+    inner: Mutex<HashMap<String, Session>>,
+}
+
+#[derive(Debug)]
+struct Session {
+    csrf_token: String,
+    created: Instant,
+}
+
+/// a request from someone who is allowed to use the routes that can change the site. if
+/// `server_password_hash` is not set, that means everyone.
+#[derive(Debug)]
+pub struct User {
+    csrf_token: Option<String>,
+}
+
+impl Sessions {
+    /// returns the id of a new session, or None if the password was wrong. also forgets any
+    /// sessions that have expired.
+    pub async fn login(&self, password: String) -> eyre::Result<Option<String>> {
+        let Some(hash) = SETTINGS.server_password_hash.as_deref() else {
+            return Ok(None);
+        };
+        // argon2 is slow on purpose, so keep it off the async runtime.
+        let is_correct = spawn_blocking(move || -> eyre::Result<bool> {
+            let hash = PasswordHash::new(hash).map_err(|error| eyre!("{error}"))?;
+            Ok(Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok())
+        })
+        .await??;
+        if !is_correct {
+            return Ok(None);
+        }
+
+        let session_id = random_token();
+        let mut inner = self.inner.lock().unwrap_or_else(|error| error.into_inner());
+        inner.retain(|_, session| !session.is_expired());
+        inner.insert(
+            session_id.clone(),
+            Session {
+                csrf_token: random_token(),
+                created: Instant::now(),
+            },
+        );
+
+        Ok(Some(session_id))
+    }
+
+    pub fn logout(&self, session_id: &str) {
+        self.inner
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .remove(session_id);
+    }
+
+    fn csrf_token(&self, session_id: &str) -> Option<String> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .get(session_id)
+            .filter(|session| !session.is_expired())
+            .map(|session| session.csrf_token.clone())
+    }
+}
+
+impl Session {
+    fn is_expired(&self) -> bool {
+        self.created.elapsed() > SESSION_LIFETIME
+    }
+}
+
+impl User {
+    /// the token that forms need to send back in a `csrf` field, if any.
+    pub fn csrf_token(&self) -> Option<String> {
+        self.csrf_token.clone()
+    }
+
+    /// checks the `csrf` field sent with a form.
+    pub fn check_csrf(&self, token: Option<&str>) -> rocket_eyre::Result<()> {
+        let Some(expected) = self.csrf_token.as_deref() else {
+            return Ok(());
+        };
+        if token.is_some_and(|token| constant_time_eq(token.as_bytes(), expected.as_bytes())) {
+            Ok(())
+        } else {
+            Err(EyreReport::Forbidden(eyre!("missing or wrong csrf token")))
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for User {
+    type Error = &'static str;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if SETTINGS.server_password_hash.is_none() {
+            return Outcome::Success(User { csrf_token: None });
+        }
+        let Some(sessions) = request.rocket().state::<Sessions>() else {
+            return Outcome::Error((Status::InternalServerError, "no session store"));
+        };
+
+        match request
+            .cookies()
+            .get(SESSION_COOKIE)
+            .and_then(|cookie| sessions.csrf_token(cookie.value()))
+        {
+            Some(csrf_token) => Outcome::Success(User {
+                csrf_token: Some(csrf_token),
+            }),
+            None => Outcome::Error((Status::Unauthorized, "not logged in")),
+        }
+    }
+}
+
+/// uuid v4 is 122 bits from the operating system’s random number generator, which is plenty for
+/// session ids and csrf tokens.
+fn random_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |result, (a, b)| result | (a ^ b)) == 0
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq(b"", b""));
+    assert!(constant_time_eq(b"token", b"token"));
+    assert!(!constant_time_eq(b"token", b"tokem"));
+    assert!(!constant_time_eq(b"token", b"token2"));
+}
+
+#[test]
+fn test_expired_sessions() {
+    let sessions = Sessions::default();
+    let mut inner = sessions.inner.lock().unwrap();
+    inner.insert(
+        "new".to_owned(),
+        Session {
+            csrf_token: "new token".to_owned(),
+            created: Instant::now(),
+        },
+    );
+    if let Some(created) = Instant::now().checked_sub(SESSION_LIFETIME + Duration::from_secs(1)) {
+        inner.insert(
+            "old".to_owned(),
+            Session {
+                csrf_token: "old token".to_owned(),
+                created,
+            },
+        );
+    }
+    drop(inner);
+    assert_eq!(sessions.csrf_token("new").as_deref(), Some("new token"));
+    assert_eq!(sessions.csrf_token("old"), None);
+}
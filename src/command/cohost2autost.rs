@@ -141,9 +141,11 @@ fn convert_single_chost(
         references: shared_post_filenames,
         title: Some(post.headline),
         published: Some(post.publishedAt),
+        updated: None,
         author: Some((&post.postingProject).into()),
         tags: post.tags,
         is_transparent_share: post.transparentShareOfPostId.is_some(),
+        is_unpublished: false,
     };
     output.write_all(meta.render()?.as_bytes())?;
     output.write_all(b"\n\n")?;
@@ -0,0 +1,81 @@
+use std::fs::{remove_dir_all, remove_file};
+
+use jane_eyre::eyre::{self, bail};
+use tracing::info;
+
+use crate::{
+    command::{
+        render::{all_post_paths, render_all},
+        unpublish::{check_post_exists, remove_rendered_page},
+    },
+    path::PostsPath,
+    webmention::{send_removal_webmentions_after_render, take_sent_webmentions, RemovedPost},
+    TemplatedPost,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Delete {
+    #[arg(required = true)]
+    post_paths: Vec<String>,
+}
+
+pub fn main(args: Delete) -> eyre::Result<()> {
+    let mut removed_posts = vec![];
+    for path in args.post_paths {
+        let path = PostsPath::from_site_root_relative_path(&path)?;
+        removed_posts.push(delete(&path)?);
+    }
+    render_all()?;
+    send_removal_webmentions_after_render(removed_posts)?;
+
+    Ok(())
+}
+
+/// deletes the post and its rendered page, plus the directory of referenced posts that
+/// `cohost2autost` created for it, if any, and its received and sent webmentions. the caller is
+/// responsible for rendering the site again, then sending webmentions for the returned post (see
+/// [`send_removal_webmentions_after_render`]), so that the sites it linked to can find out that
+/// it is gone.
+///
+/// posts that other posts reply to can’t be deleted, since those posts would fail to render.
+/// attachments are left alone, since other posts may still use them.
+pub fn delete(path: &PostsPath) -> eyre::Result<RemovedPost> {
+    check_post_exists(path)?;
+    let referencing_paths = posts_referencing(path)?;
+    if !referencing_paths.is_empty() {
+        bail!("can’t delete {path:?}, because other posts reply to it: {referencing_paths:?}");
+    }
+    info!(?path, "deleting post");
+    remove_rendered_page(path)?;
+    remove_file(path)?;
+    if let Some(references_dir) = path.references_dir_for_post() {
+        if references_dir.as_ref().is_dir() {
+            info!("removing {references_dir:?}");
+            remove_dir_all(&references_dir)?;
+        }
+    }
+    let webmentions_path = path.webmentions_path()?;
+    if webmentions_path.as_ref().is_file() {
+        info!("removing {webmentions_path:?}");
+        remove_file(&webmentions_path)?;
+    }
+
+    take_sent_webmentions(path)
+}
+
+/// the posts that reply to the given post, in `<link rel="references">`.
+fn posts_referencing(path: &PostsPath) -> eyre::Result<Vec<PostsPath>> {
+    let mut result = vec![];
+    for other_path in all_post_paths()? {
+        if other_path != *path
+            && TemplatedPost::load(&other_path)?
+                .meta
+                .references
+                .contains(path)
+        {
+            result.push(other_path);
+        }
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,34 @@
+use std::fs::write;
+
+use jane_eyre::eyre;
+use tracing::info;
+
+use crate::{output::OpmlTemplate, reader::Subscriptions, SETTINGS};
+
+#[derive(clap::Args, Debug)]
+pub struct ExportOpml {
+    path: String,
+
+    #[arg(long, help = "only export the subscriptions listed in the blogroll")]
+    public_only: bool,
+}
+
+/// writes your subscriptions to an opml file.
+pub fn main(args: ExportOpml) -> eyre::Result<()> {
+    let subscriptions = Subscriptions::load()?;
+    let subscriptions = subscriptions
+        .subscriptions
+        .iter()
+        .filter(|subscription| subscription.public || !args.public_only)
+        .collect::<Vec<_>>();
+    let content =
+        OpmlTemplate::render(&SETTINGS.page_title(Some("subscriptions")), &subscriptions)?;
+    write(&args.path, content)?;
+    info!(
+        "wrote {} subscriptions to {}",
+        subscriptions.len(),
+        args.path
+    );
+
+    Ok(())
+}
@@ -0,0 +1,21 @@
+use clap::Parser as _;
+use jane_eyre::eyre;
+
+use crate::{migrations::run_migrations, reader::fetch, Command};
+
+#[derive(clap::Args, Debug)]
+pub struct Fetch {
+    /// urls of the subscriptions to fetch (default: all of them)
+    urls: Vec<String>,
+}
+
+/// fetches new and changed entries from subscriptions into posts in `posts/reader/`.
+#[tokio::main]
+pub async fn main() -> eyre::Result<()> {
+    let Command::Fetch(args) = Command::parse() else {
+        unreachable!("guaranteed by subcommand call in entry point")
+    };
+    run_migrations()?;
+
+    fetch(&args.urls).await
+}
@@ -0,0 +1,29 @@
+use std::io::{stdin, Write as _};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHasher as _,
+};
+use jane_eyre::eyre::{self, bail, eyre};
+
+#[derive(clap::Args, Debug)]
+pub struct HashPassword {}
+
+pub fn main() -> eyre::Result<()> {
+    eprint!("password (will be shown as you type): ");
+    std::io::stderr().flush()?;
+    let mut password = String::default();
+    stdin().read_line(&mut password)?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        bail!("password must not be empty");
+    }
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|error| eyre!("{error}"))?;
+    println!("{hash}");
+
+    Ok(())
+}
@@ -1,36 +1,57 @@
 use std::{
-    fs::{create_dir_all, File},
-    io::{self, Write},
+    collections::BTreeSet,
+    fs::{create_dir_all, read, remove_file, File},
+    io,
+    mem::take,
+    path::absolute,
     rc::Rc,
 };
 
 use askama::Template;
 use base64::{prelude::BASE64_STANDARD, Engine};
+use bytes::Bytes;
 use clap::Parser as _;
 use html5ever::Attribute;
 use jane_eyre::eyre::{self, bail, OptionExt};
 use markup5ever_rcdom::{Handle, NodeData};
 use reqwest::Client;
 use serde::Deserialize;
+use tokio::task::spawn_blocking;
 use tracing::{debug, info, trace, warn};
 use url::Url;
 
 use crate::{
-    akkoma::{AkkomaImgTemplate, ApiInstance, ApiStatus},
+    akkoma::{
+        AkkomaAttachmentTemplate, AkkomaAudioTemplate, AkkomaImgTemplate, AkkomaVideoTemplate,
+        ApiContext, ApiInstance, ApiStatus, ContentWarningTemplate,
+    },
     attachments::{AttachmentsContext, RealAttachmentsContext},
     dom::{
         html_attributes_with_embedding_urls, html_attributes_with_non_embedding_urls,
         parse_html_document, parse_html_fragment, serialize_html_fragment, serialize_node_contents,
         text_content, AttrsRefExt, BreadthTraverse, QualName, QualNameExt, TendrilExt,
     },
+    feed::parse_feed,
+    http::{get_json, get_response, get_with_retries},
     migrations::run_migrations,
     path::{PostsPath, POSTS_PATH_IMPORTED},
     Author, Command, PostMeta, TemplatedPost,
 };
 
+/// maximum number of replied-to or reposted posts to import before a post, in case of long chains.
+const MAX_ANCESTORS: usize = 50;
+
 #[derive(clap::Args, Debug)]
 pub struct Import {
-    url: String,
+    #[arg(required_unless_present = "feed")]
+    url: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "url",
+        help = "import every entry of an atom or rss feed (url or local file)"
+    )]
+    feed: Option<String>,
 }
 
 #[derive(clap::Args, Debug)]
@@ -44,43 +65,161 @@ pub async fn main() -> eyre::Result<()> {
         unreachable!("guaranteed by subcommand call in entry point")
     };
     run_migrations()?;
-
-    let url = args.url;
     create_dir_all(&*POSTS_PATH_IMPORTED)?;
 
-    let FetchPostResult {
-        base_href,
-        content: e_content,
-        url: u_url,
-        meta,
-    } = fetch_post(&url).await?;
+    if let Some(feed) = args.feed {
+        return import_feed(&feed).await;
+    }
+    let Some(url) = args.url else {
+        unreachable!("guaranteed by required_unless_present")
+    };
 
-    let mut result = None;
+    let client = Client::new();
+    let body = fetch_page(&client, &url).await?;
+    let posts = if mf2_find(parse_html_document(&body)?.document.clone(), "h-feed").is_some() {
+        let feed = parse_feed(&body, &Url::parse(&url)?)?.ok_or_eyre(".h-feed has no .h-entry")?;
+        info!("found h-feed with {} entries", feed.entries.len());
+        feed.entries
+    } else {
+        vec![page_post(&client, &body, &url).await?]
+    };
+
+    for mut post in posts {
+        let ancestors = fetch_ancestors(&client, &mut post).await;
+        // writing posts downloads attachments with blocking requests.
+        spawn_blocking(move || write_thread(ancestors, post)).await??;
+    }
+
+    Ok(())
+}
+
+/// fetches the posts that the given post replies to or reposts, recursively, oldest first. if any
+/// of them can’t be fetched, the chain stops there.
+async fn fetch_ancestors(client: &Client, post: &mut FetchPostResult) -> Vec<FetchPostResult> {
+    if !post.ancestors.is_empty() {
+        return take(&mut post.ancestors);
+    }
+
+    let mut result = vec![];
+    let mut seen = BTreeSet::from([post.url.clone()]);
+    let mut parent_url = post.parent_url.clone();
+    while let Some(url) = parent_url.take() {
+        if result.len() >= MAX_ANCESTORS {
+            warn!(%url, "too many replied-to or reposted posts; stopping here");
+            break;
+        }
+        if !seen.insert(url.clone()) {
+            warn!(%url, "replied-to or reposted posts form a cycle; stopping here");
+            break;
+        }
+        match fetch_post(client, url.as_str()).await {
+            Ok(parent) => {
+                parent_url = parent.parent_url.clone();
+                result.push(parent);
+            }
+            Err(error) => warn!(?error, %url, "failed to import replied-to or reposted post"),
+        }
+    }
+    result.reverse();
+
+    result
+}
+
+/// writes each post to its own post in `posts/imported/`, with the posts before it as its
+/// references, so that the last post renders as a thread.
+fn write_thread(ancestors: Vec<FetchPostResult>, post: FetchPostResult) -> eyre::Result<()> {
+    let mut references = vec![];
+    for post in ancestors.into_iter().chain([post]) {
+        let FetchPostResult {
+            base_href,
+            content: e_content,
+            url: u_url,
+            mut meta,
+            ..
+        } = post;
+        meta.references = references.clone();
+        let (path, is_new) = find_imported_post(&u_url)?;
+        write_post(meta, &e_content, &base_href, &path, is_new)?;
+        references.push(path);
+    }
+
+    Ok(())
+}
+
+/// finds the post in `posts/imported/` that we imported from the given url, or if there is none,
+/// creates an empty post for it. returns the path, and whether the post is new.
+fn find_imported_post(u_url: &Url) -> eyre::Result<(PostsPath, bool)> {
     for post_id in 1.. {
         let path = PostsPath::imported_post_path(post_id);
         match File::create_new(&path) {
-            Ok(file) => {
+            Ok(_) => {
                 info!("creating new post: {path:?}");
-                result = Some((path, file));
-                break;
+                return Ok((path, true));
             }
             Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
                 let post = TemplatedPost::load(&path)?;
                 if post.meta.archived == Some(u_url.to_string()) {
                     info!("updating existing post: {path:?}");
-                    let file = File::create(&path)?;
-                    result = Some((path, file));
-                    break;
+                    return Ok((path, false));
                 }
             }
             Err(other) => Err(other)?,
         }
     }
 
-    let (path, file) = result.ok_or_eyre("too many posts :(")?;
-    write_post(file, meta, e_content, base_href, path)?;
+    bail!("too many posts :(")
+}
+
+/// imports every entry of an atom or rss feed, given its url or the path to a local file.
+async fn import_feed(url_or_path: &str) -> eyre::Result<()> {
+    let (body, url) = match Url::parse(url_or_path) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            let body = get_with_retries(&Client::new(), url.as_str(), Ok).await?;
+            (body.to_vec(), url)
+        }
+        _ => {
+            let url = Url::from_file_path(absolute(url_or_path)?)
+                .ok()
+                .ok_or_eyre("failed to convert path to url")?;
+            (read(url_or_path)?, url)
+        }
+    };
+    let feed = parse_feed(&body, &url)?.ok_or_eyre("not an atom or rss feed")?;
+    info!("found {} entries", feed.entries.len());
 
-    Ok(())
+    // writing posts downloads attachments with blocking requests.
+    spawn_blocking(move || {
+        let mut failures = 0;
+        for mut entry in feed.entries {
+            // a local copy of a feed can’t have attachments next to it, so resolve relative urls
+            // against where the entry was published instead.
+            if entry.base_href.scheme() == "file" {
+                entry.base_href = entry.url.clone();
+            }
+            if let Err(error) = import_feed_entry(&entry) {
+                warn!(?error, url = entry.url.as_str(), "failed to import entry");
+                failures += 1;
+            }
+        }
+        if failures > 0 {
+            bail!("failed to import {failures} entries");
+        }
+
+        Ok(())
+    })
+    .await?
+}
+
+fn import_feed_entry(entry: &FetchPostResult) -> eyre::Result<()> {
+    let (path, is_new) = find_imported_post(&entry.url)?;
+
+    write_imported_post(
+        entry.meta.clone(),
+        &entry.content,
+        &entry.base_href,
+        &path,
+        is_new,
+    )
 }
 
 pub mod reimport {
@@ -100,28 +239,42 @@ pub mod reimport {
             base_href,
             content: e_content,
             url: u_url,
-            meta,
-        } = fetch_post(&url).await?;
+            mut meta,
+            ..
+        } = fetch_post(&Client::new(), &url).await?;
         assert_eq!(url, u_url.to_string());
+        // keep the thread the post was imported into.
+        meta.references = post.meta.references;
 
         info!("updating existing post: {path:?}");
-        let file = File::create(&path)?;
-        write_post(file, meta, e_content, base_href, path)?;
+        // writing posts downloads attachments with blocking requests.
+        spawn_blocking(move || write_post(meta, &e_content, &base_href, &path, false)).await??;
 
         Ok(())
     }
 }
 
-async fn fetch_post(url: &str) -> eyre::Result<FetchPostResult> {
+async fn fetch_page(client: &Client, url: &str) -> eyre::Result<Bytes> {
     info!("GET {url}");
-    let client = reqwest::Client::new();
     let response = client.get(url).send().await?;
-    let dom = parse_html_document(&response.bytes().await?)?;
+
+    Ok(response.bytes().await?)
+}
+
+async fn fetch_post(client: &Client, url: &str) -> eyre::Result<FetchPostResult> {
+    let body = fetch_page(client, url).await?;
+
+    page_post(client, &body, url).await
+}
+
+/// parses the page fetched from `url` as a post.
+async fn page_post(client: &Client, body: &[u8], url: &str) -> eyre::Result<FetchPostResult> {
+    let dom = parse_html_document(body)?;
 
     if let Some(result) = fetch_h_entry_post(dom.document.clone(), url)? {
         return Ok(result);
     }
-    if let Some(result) = fetch_akkoma_post(dom.document.clone(), url, &client).await? {
+    if let Some(result) = fetch_mastodon_api_post(dom.document.clone(), url, client).await? {
         return Ok(result);
     }
 
@@ -134,67 +287,63 @@ fn fetch_h_entry_post(document: Handle, url: &str) -> eyre::Result<Option<FetchP
     };
     info!("found h-entry post");
 
-    let mut base_href = Url::parse(url)?;
+    let base_href = document_base_href(document, &Url::parse(url)?)?;
+    let result = h_entry_post(h_entry, &base_href)?;
+    if result.meta.author.is_none() {
+        bail!(".h-entry has no .p-author");
+    }
+
+    Ok(Some(result))
+}
+
+/// the url that relative urls in the document are relative to, taking any `<base href>` into
+/// account.
+pub fn document_base_href(document: Handle, url: &Url) -> eyre::Result<Url> {
     for node in BreadthTraverse::elements(document) {
         let NodeData::Element { name, attrs, .. } = &node.data else {
             unreachable!()
         };
         if name == &QualName::html("base") {
             if let Some(href) = attrs.borrow().attr_str("href")? {
-                base_href = base_href.join(href)?;
-                break;
+                return Ok(url.join(href)?);
             }
         }
     }
 
-    let e_content =
-        mf2_e(h_entry.clone(), "e-content")?.ok_or_eyre(".h-entry has no .e-content")?;
-    trace!(?e_content);
+    Ok(url.clone())
+}
+
+/// parses an h-entry into a post. the author may be None, if the h-entry has no `.p-author`.
+///
+/// reposts need not have a `.e-content`, in which case they become transparent shares.
+pub fn h_entry_post(h_entry: Handle, base_href: &Url) -> eyre::Result<FetchPostResult> {
+    let in_reply_to = mf2_u_or_cite(h_entry.clone(), "u-in-reply-to", base_href)?;
+    let repost_of = mf2_u_or_cite(h_entry.clone(), "u-repost-of", base_href)?;
+    let e_content = match mf2_e(h_entry.clone(), "e-content")? {
+        Some(e_content) => e_content,
+        None if repost_of.is_some() => String::new(),
+        None => bail!(".h-entry has no .e-content"),
+    };
+    trace!(?e_content, ?in_reply_to, ?repost_of);
 
-    let u_url = mf2_u(h_entry.clone(), "u-url", &base_href)?;
+    let u_url = mf2_u(h_entry.clone(), "u-url", base_href)?;
     let dt_published = mf2_dt(h_entry.clone(), "dt-published")?;
+    let dt_updated = mf2_dt(h_entry.clone(), "dt-updated")?;
     let p_name = mf2_p(h_entry.clone(), "p-name")?;
-    let p_author = mf2_find(h_entry.clone(), "p-author").ok_or_eyre(".h-entry has no .p-author")?;
     let p_category = mf2_find_all(h_entry.clone(), "p-category");
-    trace!(?u_url, ?dt_published, ?p_name, ?p_author, ?p_category);
+    trace!(?u_url, ?dt_published, ?dt_updated, ?p_name, ?p_category);
 
     // the canonical url is what the h-entry says it is.
     let canonical_url = u_url.ok_or_eyre(".h-entry has no .u-url")?;
-    let author = if has_class(p_author.clone(), "h-card")? {
-        let card_url = mf2_u(p_author.clone(), "u-url", &base_href)?;
-        let card_name = mf2_p(p_author.clone(), "p-name")?.ok_or_eyre(".h-card has no .p-name")?;
-        let url = card_url.unwrap_or(canonical_url.clone());
-        Author {
-            href: url.to_string(),
-            name: card_name.clone(),
-            display_name: card_name.clone(),
-            display_handle: url.authority().to_owned(),
-        }
-    } else {
-        let p_author = mf2_p(p_author.clone(), "p-author")?
-            .ok_or_eyre("failed to parse .p-author as p-property")?;
-        Author {
-            href: canonical_url.to_string(),
-            name: p_author.clone(),
-            display_name: p_author.clone(),
-            display_handle: canonical_url.authority().to_owned(),
-        }
-    };
+    let author = h_entry_author(h_entry.clone(), base_href, &canonical_url)?;
     trace!(?author);
 
     let mut tags = vec![];
-    'category: for p_category in p_category {
+    for p_category in p_category {
         // skip any .p-category that may be in a nested .h-entry (nex-3.com extension).
         // <https://nex-3.com/blog/reblogging-posts-with-h-entry/>
-        let mut node = p_category.clone();
-        // access the parent, per <markup5ever_rcdom-0.3.0/lib.rs:170>.
-        while let Some(weak) = node.parent.take() {
-            let parent = weak.upgrade().expect("dangling weak pointer");
-            node.parent.set(Some(weak));
-            if has_class(parent.clone(), "h-entry")? && !Rc::ptr_eq(&parent, &h_entry) {
-                continue 'category;
-            }
-            node = parent;
+        if is_nested_in_other(p_category.clone(), "h-entry", &h_entry)? {
+            continue;
         }
 
         let p_category = mf2_p(p_category.clone(), "p-category")?
@@ -202,59 +351,57 @@ fn fetch_h_entry_post(document: Handle, url: &str) -> eyre::Result<Option<FetchP
         tags.push(p_category);
     }
 
+    let is_transparent_share = repost_of.is_some() && e_content.trim().is_empty();
     let meta = PostMeta {
         archived: Some(canonical_url.to_string()),
-        references: vec![], // TODO: define a cohost-like h-entry extension for this?
+        // filled in by the importer, once the posts in `parent_url` have their own paths.
+        references: vec![],
         title: p_name,
         published: dt_published,
-        author: Some(author),
+        updated: dt_updated,
+        author,
         tags,
-        is_transparent_share: false,
+        is_transparent_share,
+        is_unpublished: false,
     };
     debug!(?meta);
 
-    Ok(Some(FetchPostResult {
-        base_href,
+    Ok(FetchPostResult {
+        base_href: base_href.clone(),
         content: e_content,
         url: canonical_url,
         meta,
-    }))
+        parent_url: repost_of.or(in_reply_to),
+        ancestors: vec![],
+    })
+}
+
+/// whether the node is inside an element with the given class, other than `except`.
+pub fn is_nested_in_other(node: Handle, class: &str, except: &Handle) -> eyre::Result<bool> {
+    let mut node = node;
+    // access the parent, per <markup5ever_rcdom-0.3.0/lib.rs:170>.
+    while let Some(weak) = node.parent.take() {
+        let parent = weak.upgrade().expect("dangling weak pointer");
+        node.parent.set(Some(weak));
+        if has_class(parent.clone(), class)? && !Rc::ptr_eq(&parent, except) {
+            return Ok(true);
+        }
+        node = parent;
+    }
+
+    Ok(false)
 }
 
-async fn fetch_akkoma_post(
+/// fetches the post via the mastodon client api, if the page is a status on a server with one.
+///
+/// akkoma pages tell us where the api is. for other servers, like mastodon, gotosocial, and
+/// pleroma, we try the api on the same origin as the page, with the last path segment of the page
+/// url as the status id.
+async fn fetch_mastodon_api_post(
     document: Handle,
     url: &str,
     client: &Client,
 ) -> eyre::Result<Option<FetchPostResult>> {
-    // check if the page is actually an akkoma page.
-    #[derive(Deserialize)]
-    struct InitialResults {
-        #[serde(rename = "/api/v1/instance")]
-        api_v1_instance: String,
-    }
-    let Some(initial_results) = (|| -> eyre::Result<Option<InitialResults>> {
-        for node in BreadthTraverse::elements(document) {
-            let NodeData::Element { name, attrs, .. } = &node.data else {
-                unreachable!()
-            };
-            if name == &QualName::html("script")
-                && attrs.borrow().attr_str("id")? == Some("initial-results")
-            {
-                return Ok(Some(serde_json::from_str(&text_content(node)?)?));
-            }
-        }
-        Ok(None)
-    })()?
-    else {
-        return Ok(None);
-    };
-    let instance = BASE64_STANDARD.decode(initial_results.api_v1_instance)?;
-    let instance = serde_json::from_slice::<ApiInstance>(&instance)?;
-    info!(?instance.uri, ?instance.version, "found akkoma instance");
-
-    // try to fetch the post via the mastodon api.
-    let instance_url = Url::parse(&instance.uri)?;
-    trace!(?instance_url);
     let fetched_page_url = Url::parse(url)?;
     trace!(?fetched_page_url);
     let status_id = fetched_page_url
@@ -263,65 +410,182 @@ async fn fetch_akkoma_post(
         .next_back()
         .ok_or_eyre("page url has no last path segment")?;
     trace!(?status_id);
+
+    if let Some(instance) = akkoma_instance(document)? {
+        info!(?instance.uri, ?instance.version, "found akkoma instance");
+        let instance_url = Url::parse(&instance.uri)?;
+        let api_url = instance_url.join(&format!("api/v1/statuses/{status_id}"))?;
+        let status = get_json::<ApiStatus>(client, api_url.as_str()).await?;
+        return Ok(Some(
+            mastodon_api_thread(client, &instance_url, status).await?,
+        ));
+    }
+
+    // if the api is not there, the page is not a status we know how to import, so don’t retry.
+    let instance_url = fetched_page_url.join("/")?;
     let api_url = instance_url.join(&format!("api/v1/statuses/{status_id}"))?;
     info!("GET {api_url}");
-    let response = client.get(api_url).send().await?;
-    let status = response.json::<ApiStatus>().await?;
+    let status = match get_response(client, api_url.as_str()).await {
+        Ok(response) => response.json::<ApiStatus>().await,
+        Err(error) => {
+            debug!(?error, "no mastodon api");
+            return Ok(None);
+        }
+    };
+    let Ok(status) = status else {
+        debug!("no mastodon api");
+        return Ok(None);
+    };
+    info!("found mastodon api");
+
+    Ok(Some(
+        mastodon_api_thread(client, &instance_url, status).await?,
+    ))
+}
+
+/// the instance info embedded in akkoma pages, if the page is an akkoma page.
+fn akkoma_instance(document: Handle) -> eyre::Result<Option<ApiInstance>> {
+    #[derive(Deserialize)]
+    struct InitialResults {
+        #[serde(rename = "/api/v1/instance")]
+        api_v1_instance: String,
+    }
+    for node in BreadthTraverse::elements(document) {
+        let NodeData::Element { name, attrs, .. } = &node.data else {
+            unreachable!()
+        };
+        if name == &QualName::html("script")
+            && attrs.borrow().attr_str("id")? == Some("initial-results")
+        {
+            let initial_results = serde_json::from_str::<InitialResults>(&text_content(node)?)?;
+            let instance = BASE64_STANDARD.decode(initial_results.api_v1_instance)?;
+            return Ok(Some(serde_json::from_slice(&instance)?));
+        }
+    }
+
+    Ok(None)
+}
 
+/// converts a status into a post, along with the statuses it replies to, which are fetched via
+/// the mastodon client api at `instance_url`.
+async fn mastodon_api_thread(
+    client: &Client,
+    instance_url: &Url,
+    status: ApiStatus,
+) -> eyre::Result<FetchPostResult> {
+    let mut ancestors = vec![];
+    if status.in_reply_to_id.is_some() {
+        let api_url = instance_url.join(&format!("api/v1/statuses/{}/context", status.id))?;
+        match get_json::<ApiContext>(client, api_url.as_str()).await {
+            Ok(context) => {
+                for status in context.ancestors {
+                    ancestors.push(mastodon_api_status_post(status)?);
+                }
+            }
+            Err(error) => warn!(?error, "failed to fetch replied-to statuses"),
+        }
+    }
+
+    let mut result = mastodon_api_status_post(status)?;
+    result.ancestors = ancestors;
+
+    Ok(result)
+}
+
+fn mastodon_api_status_post(status: ApiStatus) -> eyre::Result<FetchPostResult> {
     // the canonical url is what the api says it is.
-    let canonical_url = status.url;
+    let canonical_url = status.url.unwrap_or(status.uri);
     let author = Author::from(&status.account);
 
-    let mut contents = vec![];
+    let mut attachments = vec![];
     for attachment in status.media_attachments {
-        if attachment.r#type != "image" {
-            warn!(?attachment.r#type, "skipping unknown attachment type");
-            continue;
-        }
-        let template = AkkomaImgTemplate {
-            data_akkoma_src: attachment.preview_url.clone(),
-            href: attachment.url,
-            src: attachment.preview_url,
-            alt: attachment.description,
+        // <https://docs.joinmastodon.org/entities/MediaAttachment/#type>
+        let html = match &*attachment.r#type {
+            "image" => {
+                let preview_url = attachment.preview_url.unwrap_or(attachment.url.clone());
+                AkkomaImgTemplate {
+                    data_akkoma_src: preview_url.clone(),
+                    href: attachment.url,
+                    src: preview_url,
+                    alt: attachment.description,
+                }
+                .render()?
+            }
+            r#type @ ("video" | "gifv") => AkkomaVideoTemplate {
+                data_akkoma_src: attachment.url.clone(),
+                src: attachment.url,
+                description: attachment.description,
+                is_gifv: r#type == "gifv",
+            }
+            .render()?,
+            "audio" => AkkomaAudioTemplate {
+                data_akkoma_src: attachment.url.clone(),
+                src: attachment.url,
+                description: attachment.description,
+            }
+            .render()?,
+            other => {
+                warn!(r#type = other, "linking to attachment of unknown type");
+                AkkomaAttachmentTemplate {
+                    href: attachment.url,
+                    description: attachment.description,
+                }
+                .render()?
+            }
         };
-        contents.push(template.render()?);
+        attachments.push(html);
+    }
+    let mut attachments = attachments.join("");
+    // a content warning hides the attachments too, so they only need hiding if there is none.
+    if status.sensitive && status.spoiler_text.is_empty() && !attachments.is_empty() {
+        attachments = ContentWarningTemplate {
+            summary: "sensitive media".to_owned(),
+            content: attachments,
+        }
+        .render()?;
+    }
+    let mut content = format!("{attachments}{}", status.content);
+    if !status.spoiler_text.is_empty() {
+        content = ContentWarningTemplate {
+            summary: status.spoiler_text,
+            content,
+        }
+        .render()?;
     }
-    contents.push(status.content);
-    let content = contents.join("");
 
     let url = Url::parse(&canonical_url)?;
     let meta = PostMeta {
         archived: Some(canonical_url),
-        references: vec![], // TODO: handle akkoma reply chain?
+        // filled in by the importer, once the ancestors have their own paths.
+        references: vec![],
         title: None,
         published: Some(status.created_at),
+        updated: status.edited_at,
         author: Some(author),
         tags: status.tags.into_iter().map(|tag| tag.name).collect(),
         is_transparent_share: false,
+        is_unpublished: false,
     };
 
-    Ok(Some(FetchPostResult {
+    Ok(FetchPostResult {
         base_href: url.clone(),
         content,
         url,
         meta,
-    }))
+        parent_url: None,
+        ancestors: vec![],
+    })
 }
 
 fn write_post(
-    mut file: File,
     meta: PostMeta,
-    e_content: String,
-    base_href: Url,
-    path: PostsPath,
+    e_content: &str,
+    base_href: &Url,
+    path: &PostsPath,
+    is_new: bool,
 ) -> eyre::Result<()> {
     info!("writing {path:?}");
-    file.write_all(meta.render()?.as_bytes())?;
-    file.write_all(b"\n\n")?;
-    let basename = path.basename().ok_or_eyre("path has no basename")?;
-    let unsafe_html = process_content(&e_content, basename, &base_href, &RealAttachmentsContext)?;
-    let post = TemplatedPost::filter(&unsafe_html, Some(path.clone()))?;
-    file.write_all(post.safe_html.as_bytes())?;
+    write_imported_post(meta, e_content, base_href, path, is_new)?;
     info!("click here to reply: {}", path.compose_reply_url());
     info!(
         "or transparent share: {}",
@@ -331,11 +595,60 @@ fn write_post(
     Ok(())
 }
 
-struct FetchPostResult {
-    base_href: Url,
-    content: String,
-    url: Url,
+/// writes a post fetched from elsewhere to `path`. if anything fails, like downloading an
+/// attachment, the post is left as it was, or removed if it was new, so that a failed import never
+/// leaves an empty post behind.
+fn write_imported_post(
+    meta: PostMeta,
+    e_content: &str,
+    base_href: &Url,
+    path: &PostsPath,
+    is_new: bool,
+) -> eyre::Result<()> {
+    let result = imported_post_contents(meta, e_content, base_href, path)
+        .and_then(|contents| path.replace_contents(contents.as_bytes()));
+    if let Err(error) = result {
+        if is_new {
+            remove_file(path)?;
+        }
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// the source of a post fetched from elsewhere, to be written to `path`. this caches its
+/// attachments and filters its content.
+///
+/// attachments are downloaded with blocking requests, so in async code, call this in
+/// [`tokio::task::spawn_blocking`].
+pub fn imported_post_contents(
     meta: PostMeta,
+    e_content: &str,
+    base_href: &Url,
+    path: &PostsPath,
+) -> eyre::Result<String> {
+    let basename = path.basename().ok_or_eyre("path has no basename")?;
+    let unsafe_html = process_content(e_content, basename, base_href, &RealAttachmentsContext)?;
+    let post = TemplatedPost::filter(&unsafe_html, Some(path.clone()))?;
+
+    Ok(format!("{}\n\n{}", meta.render()?, post.safe_html))
+}
+
+/// a post fetched from elsewhere, before its content has been processed.
+#[derive(Debug)]
+pub struct FetchPostResult {
+    /// the url that relative urls in `content` are relative to.
+    pub base_href: Url,
+    pub content: String,
+    /// the canonical url of the post.
+    pub url: Url,
+    pub meta: PostMeta,
+    /// the post that this post replies to or reposts, if any.
+    pub parent_url: Option<Url>,
+    /// the posts before this post in its thread, oldest first, if we got them along with the post
+    /// (like from the mastodon api). otherwise the importer follows `parent_url`.
+    pub ancestors: Vec<FetchPostResult>,
 }
 
 fn process_content(
@@ -409,7 +722,46 @@ fn process_content(
     serialize_html_fragment(dom)
 }
 
-fn mf2_e(node: Handle, class: &str) -> eyre::Result<Option<String>> {
+/// parses the `.p-author` of an h-entry, falling back to the url of the h-entry itself if the
+/// author has no url of their own.
+pub fn h_entry_author(
+    h_entry: Handle,
+    base_href: &Url,
+    canonical_url: &Url,
+) -> eyre::Result<Option<Author>> {
+    let Some(p_author) = mf2_find(h_entry, "p-author") else {
+        return Ok(None);
+    };
+    let author = if has_class(p_author.clone(), "h-card")? {
+        let card_url = mf2_u(p_author.clone(), "u-url", base_href)?;
+        // if the h-card has no .p-name, use its implied name, which is parsed like a p-property.
+        // <https://microformats.org/wiki/microformats2-parsing#parsing_for_implied_properties>
+        let card_name = match mf2_p(p_author.clone(), "p-name")? {
+            Some(name) => name,
+            None => mf2_p(p_author.clone(), "h-card")?.ok_or_eyre(".h-card has no name")?,
+        };
+        let url = card_url.unwrap_or(canonical_url.clone());
+        Author {
+            href: url.to_string(),
+            name: card_name.clone(),
+            display_name: card_name.clone(),
+            display_handle: url.authority().to_owned(),
+        }
+    } else {
+        let p_author = mf2_p(p_author.clone(), "p-author")?
+            .ok_or_eyre("failed to parse .p-author as p-property")?;
+        Author {
+            href: canonical_url.to_string(),
+            name: p_author.clone(),
+            display_name: p_author.clone(),
+            display_handle: canonical_url.authority().to_owned(),
+        }
+    };
+
+    Ok(Some(author))
+}
+
+pub fn mf2_e(node: Handle, class: &str) -> eyre::Result<Option<String>> {
     // TODO: handle full return value in <https://microformats.org/wiki/microformats2-parsing#parsing_an_e-_property>
     let Some(node) = mf2_find(node, class) else {
         return Ok(None);
@@ -420,7 +772,7 @@ fn mf2_e(node: Handle, class: &str) -> eyre::Result<Option<String>> {
 }
 
 /// <https://microformats.org/wiki/index.php?title=microformats2-parsing&oldid=70607#parsing_a_p-_property>
-fn mf2_p(node: Handle, class: &str) -> eyre::Result<Option<String>> {
+pub fn mf2_p(node: Handle, class: &str) -> eyre::Result<Option<String>> {
     // TODO: handle other cases in <https://microformats.org/wiki/microformats2-parsing#parsing_a_p-_property>
     let Some(node) = mf2_find(node, class) else {
         return Ok(None);
@@ -455,7 +807,7 @@ fn mf2_p(node: Handle, class: &str) -> eyre::Result<Option<String>> {
     Ok(Some(result))
 }
 
-fn mf2_u(node: Handle, class: &str, base_href: &Url) -> eyre::Result<Option<Url>> {
+pub fn mf2_u(node: Handle, class: &str, base_href: &Url) -> eyre::Result<Option<Url>> {
     // TODO: handle other cases in <https://microformats.org/wiki/microformats2-parsing#parsing_a_u-_property>
     let Some(element) = mf2_find(node.clone(), class) else {
         return Ok(None);
@@ -475,7 +827,20 @@ fn mf2_u(node: Handle, class: &str, base_href: &Url) -> eyre::Result<Option<Url>
     }
 }
 
-fn mf2_dt(node: Handle, class: &str) -> eyre::Result<Option<String>> {
+/// the first url of a u-property that may be a plain link, or an embedded `.h-cite` or `.h-entry`
+/// with its own `.u-url`, like `.u-in-reply-to` or `.u-repost-of`.
+pub fn mf2_u_or_cite(node: Handle, class: &str, base_href: &Url) -> eyre::Result<Option<Url>> {
+    let Some(element) = mf2_find(node, class) else {
+        return Ok(None);
+    };
+    if has_class(element.clone(), "h-cite")? || has_class(element.clone(), "h-entry")? {
+        mf2_u(element, "u-url", base_href)
+    } else {
+        mf2_u(element, class, base_href)
+    }
+}
+
+pub fn mf2_dt(node: Handle, class: &str) -> eyre::Result<Option<String>> {
     // TODO: handle other cases in <https://microformats.org/wiki/microformats2-parsing#parsing_a_dt-_property>
     let Some(element) = mf2_find(node.clone(), class) else {
         return Ok(None);
@@ -492,20 +857,51 @@ fn mf2_dt(node: Handle, class: &str) -> eyre::Result<Option<String>> {
     Ok(Some(result))
 }
 
-fn mf2_find(node: Handle, class: &str) -> Option<Handle> {
-    // TODO: handle errors from has_class()
-    BreadthTraverse::elements(node.clone())
-        .find(|node| has_class(node.clone(), class).unwrap_or(false))
+/// finds the first element with the given class in `node`, including `node` itself.
+///
+/// for property classes, like `p-name`, this skips elements in microformats nested in `node`,
+/// like the `.h-card` of a `.p-author` or the `.h-cite` of a `.u-in-reply-to`, since those are
+/// properties of the nested microformat, not of `node`.
+pub fn mf2_find(node: Handle, class: &str) -> Option<Handle> {
+    mf2_find_all(node, class).into_iter().next()
 }
 
-fn mf2_find_all(node: Handle, class: &str) -> Vec<Handle> {
+pub fn mf2_find_all(node: Handle, class: &str) -> Vec<Handle> {
     // TODO: handle errors from has_class()
     BreadthTraverse::elements(node.clone())
-        .filter(|node| has_class(node.clone(), class).unwrap_or(false))
+        .filter(|kid| has_class(kid.clone(), class).unwrap_or(false))
+        .filter(|kid| class.starts_with("h-") || !is_in_nested_microformat(kid.clone(), &node))
         .collect()
 }
 
-fn has_class(node: Handle, class: &str) -> eyre::Result<bool> {
+/// whether the element is inside a microformat (`.h-*`) that is inside `root`.
+fn is_in_nested_microformat(node: Handle, root: &Handle) -> bool {
+    let mut node = node;
+    // access the parent, per <markup5ever_rcdom-0.3.0/lib.rs:170>.
+    while !Rc::ptr_eq(&node, root) {
+        let Some(weak) = node.parent.take() else {
+            break;
+        };
+        let parent = weak.upgrade().expect("dangling weak pointer");
+        node.parent.set(Some(weak));
+        if Rc::ptr_eq(&parent, root) {
+            break;
+        }
+        if let NodeData::Element { attrs, .. } = &parent.data {
+            // TODO: handle errors from attr_str()
+            if let Ok(Some(class)) = attrs.borrow().attr_str("class") {
+                if class.split_ascii_whitespace().any(|c| c.starts_with("h-")) {
+                    return true;
+                }
+            }
+        }
+        node = parent;
+    }
+
+    false
+}
+
+pub fn has_class(node: Handle, class: &str) -> eyre::Result<bool> {
     if let NodeData::Element { attrs, .. } = &node.data {
         if let Some(node_class) = attrs.borrow().attr_str("class")? {
             if node_class.split(" ").any(|c| c == class) {
@@ -516,3 +912,133 @@ fn has_class(node: Handle, class: &str) -> eyre::Result<bool> {
 
     Ok(false)
 }
+
+#[test]
+fn test_h_entry_post() -> eyre::Result<()> {
+    let base_href = Url::parse("https://example.net/2")?;
+    let dom = parse_html_document(
+        br#"<article class="h-entry">
+            <div class="u-in-reply-to h-cite">
+                <a class="u-url p-name" href="/1">the first post</a>
+                <span class="p-author h-card">someone else</span>
+            </div>
+            <h1 class="p-name">a reply</h1>
+            <span class="p-author h-card"><a class="u-url p-name" href="/">eggbug</a></span>
+            <a class="u-url" href="/2"><time class="dt-published" datetime="2024-01-02">2</time></a>
+            <div class="e-content">hi!</div>
+        </article>"#,
+    )?;
+    let h_entry = mf2_find(dom.document.clone(), "h-entry").ok_or_eyre("no h-entry")?;
+    let post = h_entry_post(h_entry, &base_href)?;
+    assert_eq!(post.url.as_str(), "https://example.net/2");
+    assert_eq!(post.meta.title.as_deref(), Some("a reply"));
+    assert_eq!(
+        post.meta.author.as_ref().map(|author| &*author.name),
+        Some("eggbug")
+    );
+    assert_eq!(
+        post.parent_url.as_ref().map(Url::as_str),
+        Some("https://example.net/1")
+    );
+    assert!(!post.meta.is_transparent_share);
+
+    let dom = parse_html_document(
+        br#"<article class="h-entry">
+            <a class="u-url" href="/3"></a>
+            <a class="u-repost-of" href="https://example.com/1"></a>
+        </article>"#,
+    )?;
+    let h_entry = mf2_find(dom.document.clone(), "h-entry").ok_or_eyre("no h-entry")?;
+    let post = h_entry_post(h_entry, &base_href)?;
+    assert_eq!(
+        post.parent_url.as_ref().map(Url::as_str),
+        Some("https://example.com/1")
+    );
+    assert!(post.meta.is_transparent_share);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fetch_mastodon_api_post() -> eyre::Result<()> {
+    use crate::webmention::{stand_in, stand_in_response};
+
+    let status = |id: &str, in_reply_to_id: Option<&str>| {
+        serde_json::json!({
+            "id": id,
+            "uri": format!("https://example.net/users/eggbug/statuses/{id}"),
+            "url": format!("https://example.net/@eggbug/{id}"),
+            "content": format!("<p>status {id}</p>"),
+            "in_reply_to_id": in_reply_to_id,
+            "account": {
+                "url": "https://example.net/@eggbug",
+                "display_name": "eggbug",
+                "username": "eggbug",
+                "acct": "eggbug",
+            },
+            "media_attachments": [],
+            "tags": [],
+            "created_at": format!("2024-01-0{id}T00:00:00.000Z"),
+        })
+    };
+    let mut reply = status("3", Some("2"));
+    reply["spoiler_text"] = "cw".into();
+    reply["tags"] = serde_json::json!([{"name": "tag", "url": "https://example.net/tags/tag"}]);
+    reply["media_attachments"] = serde_json::json!([
+        {"type": "gifv", "url": "https://example.net/a.mp4", "description": "a gif"},
+        {"type": "audio", "url": "https://example.net/b.mp3", "description": null},
+    ]);
+    let context = serde_json::json!({
+        "ancestors": [status("1", None), status("2", Some("1"))],
+        "descendants": [],
+    });
+    let json = |value: serde_json::Value| {
+        stand_in_response(
+            "200 OK",
+            "Content-Type: application/json\r\n",
+            &value.to_string(),
+        )
+    };
+    let (base_url, _) = stand_in(vec![
+        ("/api/v1/statuses/3", json(reply)),
+        ("/api/v1/statuses/3/context", json(context)),
+    ])?;
+    let client = Client::new();
+
+    let page_url = base_url.join("@eggbug/3")?;
+    let post = page_post(&client, b"<!doctype html>", page_url.as_str()).await?;
+    assert_eq!(post.url.as_str(), "https://example.net/@eggbug/3");
+    assert!(post.content.starts_with("<details><summary>cw</summary>"));
+    assert!(post.content.contains("<p>status 3</p>"));
+    assert!(post.content.contains(
+        r#"<video controls loop muted autoplay playsinline data-akkoma-src="https://example.net/a.mp4" src="https://example.net/a.mp4" aria-label="a gif"></video>"#
+    ));
+    assert!(post.content.contains(
+        r#"<audio controls data-akkoma-src="https://example.net/b.mp3" src="https://example.net/b.mp3"></audio>"#
+    ));
+    assert_eq!(post.meta.tags, ["tag"]);
+    assert_eq!(
+        post.meta.author.map(|author| author.display_handle),
+        Some("@eggbug@example.net".to_owned())
+    );
+    let ancestors = post
+        .ancestors
+        .iter()
+        .map(|post| post.url.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ancestors,
+        [
+            "https://example.net/@eggbug/1",
+            "https://example.net/@eggbug/2"
+        ]
+    );
+
+    // other pages are not statuses, and fail without retrying.
+    let page_url = base_url.join("about")?;
+    assert!(page_post(&client, b"<!doctype html>", page_url.as_str())
+        .await
+        .is_err());
+
+    Ok(())
+}
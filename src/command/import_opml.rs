@@ -0,0 +1,48 @@
+use std::{fs::read, path::absolute};
+
+use jane_eyre::eyre::{self, OptionExt as _};
+use tracing::info;
+use url::Url;
+
+use crate::{
+    feed::parse_opml,
+    reader::{Subscription, Subscriptions},
+};
+
+#[derive(clap::Args, Debug)]
+pub struct ImportOpml {
+    path: String,
+
+    #[arg(long, help = "list the new subscriptions in the blogroll")]
+    public: bool,
+}
+
+/// adds the feeds listed in an opml file to your subscriptions. feeds you are already subscribed
+/// to are left alone.
+pub fn main(args: ImportOpml) -> eyre::Result<()> {
+    let url = Url::from_file_path(absolute(&args.path)?)
+        .ok()
+        .ok_or_eyre("failed to convert path to url")?;
+    let feeds = parse_opml(&read(&args.path)?, &url)?;
+
+    let mut subscriptions = Subscriptions::load()?;
+    let mut count = 0;
+    for feed in feeds {
+        if subscriptions.get(feed.xml_url.as_str()).is_some() {
+            continue;
+        }
+        subscriptions.subscriptions.push(Subscription {
+            url: feed.xml_url.to_string(),
+            title: feed.title,
+            html_url: feed.html_url.map(|url| url.to_string()),
+            public: args.public,
+            fetched: None,
+            entries: Default::default(),
+        });
+        count += 1;
+    }
+    subscriptions.save()?;
+    info!("added {count} subscriptions; run `autost fetch` to fetch them");
+
+    Ok(())
+}
@@ -1,41 +1,113 @@
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap},
-    fs::{create_dir_all, read_dir, File},
-    io::Write,
+    fs::{create_dir_all, read_dir, remove_dir, remove_file, rename, File},
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    sync::Mutex,
 };
 
 use chrono::{SecondsFormat, Utc};
 use jane_eyre::eyre::{self, bail, OptionExt};
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
-use tracing::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, error, info, trace, warn};
 
 use crate::{
+    activitypub::{
+        actor_path, inbox_path, outbox_page_path, outbox_path, render_actor, render_inbox,
+        render_outbox, render_outbox_page, render_webfinger, webfinger_path, OUTBOX_PAGE_SIZE,
+    },
     meta::hard_link_attachments_into_site,
     migrations::run_migrations,
-    output::{AtomFeedTemplate, ThreadsContentTemplate, ThreadsPageTemplate},
-    path::{PostsPath, SitePath, POSTS_PATH_ROOT, SITE_PATH_ROOT, SITE_PATH_TAGGED},
-    TemplatedPost, Thread, SETTINGS,
+    output::{
+        ArchiveOverviewTemplate, ArchiveYear, AtomFeedLinks, AtomFeedTemplate, BlogrollTemplate,
+        JsonFeed, OpmlTemplate, Pagination, SearchFormTemplate, SitemapEntry, SitemapIndexTemplate,
+        SitemapTemplate, TagsDirectoryTemplate, ThreadsContentTemplate, ThreadsPageTemplate,
+    },
+    path::{
+        PostsPath, SitePath, CACHE_PATH_RENDER, CACHE_PATH_ROOT, POSTS_PATH_ROOT,
+        SITE_PATH_ACTIVITYPUB, SITE_PATH_ARCHIVE, SITE_PATH_ATTACHMENTS, SITE_PATH_ROOT,
+        SITE_PATH_SEARCH, SITE_PATH_TAGGED, SITE_PATH_WELL_KNOWN,
+    },
+    reader::Subscriptions,
+    search::SearchIndex,
+    watch::watch_and_render,
+    webmention::send_webmentions,
+    RunDetails, TemplatedPost, Thread, SETTINGS,
 };
 
 #[derive(clap::Args, Debug)]
 pub struct Render {
     specific_post_paths: Vec<String>,
+
+    #[arg(long, help = "remove files in site/ that this render did not produce")]
+    prune: bool,
+
+    #[arg(
+        long,
+        requires = "prune",
+        help = "list the files that --prune would remove"
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long,
+        help = "keep rendering whenever posts, attachments, or settings change"
+    )]
+    watch: bool,
 }
 
+/// only one render at a time, since renders write to the same files (including the cache).
+static RENDER_LOCK: Mutex<()> = Mutex::new(());
+
 pub fn main(args: Render) -> eyre::Result<()> {
     if !args.specific_post_paths.is_empty() {
+        if args.prune || args.watch {
+            bail!("--prune and --watch need to render all posts, so they can’t be used with specific posts");
+        }
         let specific_post_paths = args
             .specific_post_paths
             .into_iter()
             .map(|path| PostsPath::from_site_root_relative_path(&path))
             .collect::<eyre::Result<Vec<_>>>()?;
-        render(specific_post_paths)
+        render(specific_post_paths.clone())?;
+        send_webmentions_after_render(&specific_post_paths)?;
     } else {
-        render_all()
+        let output_paths = render_all()?;
+        if args.prune {
+            prune(&output_paths, args.dry_run)?;
+        }
+        send_webmentions_after_render(&all_post_paths()?)?;
+        if args.watch {
+            // only the posts that changed can have new webmentions to send.
+            watch_and_render(|changed_posts| {
+                if let Err(error) = send_webmentions_after_render(changed_posts) {
+                    error!(?error, "failed to send webmentions");
+                }
+            })?;
+        }
     }
+
+    Ok(())
 }
 
-pub fn render_all() -> eyre::Result<()> {
+/// sends webmentions for the given posts, if `send_webmentions` is enabled.
+fn send_webmentions_after_render(post_paths: &[PostsPath]) -> eyre::Result<()> {
+    if SETTINGS.send_webmentions() && !post_paths.is_empty() {
+        tokio::runtime::Runtime::new()?.block_on(send_webmentions(post_paths, false))?;
+    }
+
+    Ok(())
+}
+
+/// renders all posts, returning the paths of all of the files in site/ that the render produced.
+pub fn render_all() -> eyre::Result<BTreeSet<SitePath>> {
+    render(all_post_paths()?)
+}
+
+/// returns the paths of all posts that get rendered, which are the files at the top level of
+/// `posts`.
+pub fn all_post_paths() -> eyre::Result<Vec<PostsPath>> {
     let mut post_paths = vec![];
 
     create_dir_all(&*POSTS_PATH_ROOT)?;
@@ -48,18 +120,30 @@ pub fn render_all() -> eyre::Result<()> {
         }
 
         let path = POSTS_PATH_ROOT.join_dir_entry(&entry)?;
+        // skip anything that is not a post, like a temporary file left behind by a crash.
+        if !path.is_post() {
+            continue;
+        }
         post_paths.push(path);
     }
 
-    render(post_paths)
+    Ok(post_paths)
 }
 
-pub fn render(post_paths: Vec<PostsPath>) -> eyre::Result<()> {
+/// renders the given posts, returning the paths of all of the files in site/ that the render
+/// produced.
+pub fn render(post_paths: Vec<PostsPath>) -> eyre::Result<BTreeSet<SitePath>> {
+    let _guard = RENDER_LOCK
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
     run_migrations()?;
+    let mut output_paths = BTreeSet::default();
 
     let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
     create_dir_all(&*SITE_PATH_ROOT)?;
     create_dir_all(&*SITE_PATH_TAGGED)?;
+    create_dir_all(&*SITE_PATH_SEARCH)?;
+    create_dir_all(&*SITE_PATH_ARCHIVE)?;
 
     fn copy_static(output_path: &SitePath, file: &StaticFile) -> eyre::Result<()> {
         let StaticFile(filename, content) = file;
@@ -98,6 +182,7 @@ pub fn render(post_paths: Vec<PostsPath>) -> eyre::Result<()> {
     ];
     for file in static_files.iter() {
         copy_static(&SITE_PATH_ROOT, file)?;
+        output_paths.insert(SITE_PATH_ROOT.join(file.0)?);
     }
     #[cfg(unix)]
     {
@@ -109,73 +194,92 @@ pub fn render(post_paths: Vec<PostsPath>) -> eyre::Result<()> {
         std::fs::set_permissions(deploy_path, permissions)?;
     }
 
+    // take the cached threads for the posts we are rendering out of the cache, so we can move
+    // them into the render results (when they are still fresh) without cloning them.
+    let mut cache = RenderCache::load()?;
+    let post_paths = post_paths
+        .into_iter()
+        .map(|path| {
+            let cached_thread = cache.threads.remove(&path);
+            (path, cached_thread)
+        })
+        .collect::<Vec<_>>();
     let results = post_paths
         .into_par_iter()
-        .map(render_single_post)
+        .map(|(path, cached_thread)| render_single_post(path, cached_thread))
         .collect::<Vec<_>>();
 
     let RenderResult {
         mut tags,
         mut collections,
         mut interesting_output_paths,
-        mut threads_by_interesting_tag,
+        mut threads_by_tag,
     } = RenderResult::default()?;
     let mut threads_cache = HashMap::default();
     for result in results {
-        let CacheableRenderResult {
+        let Some(CacheableRenderResult {
             render_result: result,
             cached_thread,
-        } = result?;
+        }) = result?
+        else {
+            continue;
+        };
         for (tag, count) in result.tags {
             *tags.entry(tag).or_insert(0) += count;
         }
         collections.merge(result.collections);
         interesting_output_paths.extend(result.interesting_output_paths);
-        for (tag, threads) in result.threads_by_interesting_tag {
-            threads_by_interesting_tag
-                .entry(tag)
-                .or_default()
-                .extend(threads);
+        for (tag, threads) in result.threads_by_tag {
+            threads_by_tag.entry(tag).or_default().extend(threads);
         }
         let path = cached_thread
             .thread
             .path
             .clone()
             .ok_or_eyre("thread has no path")?;
+        output_paths.extend(path.rendered_path()?);
+        output_paths.extend(cached_thread.thread.needs_attachments().cloned());
         debug_assert!(!threads_cache.contains_key(&path));
         threads_cache.insert(path, cached_thread);
     }
 
-    // author step: generate atom feeds.
-    let atom_feed_path =
-        collections.write_atom_feed("index", &SITE_PATH_ROOT, &now, &threads_cache)?;
-    interesting_output_paths.insert(atom_feed_path);
-
-    // generate /tagged/<tag>.feed.xml and /tagged/<tag>.html.
-    for (tag, threads) in threads_by_interesting_tag {
-        let atom_feed_path = SITE_PATH_TAGGED.join(&format!("{tag}.feed.xml"))?;
-        let cached_threads = threads
-            .iter()
-            .map(|thread| &threads_cache[&thread.path])
-            .collect::<Vec<_>>();
-        let atom_feed = AtomFeedTemplate::render(
-            cached_threads,
+    // generate /tagged/<tag>.feed.xml, /tagged/<tag>.feed.json, and /tagged/<tag>.html.
+    for (tag, threads) in threads_by_tag {
+        let feed_paths = write_feeds(
+            &SITE_PATH_TAGGED,
+            &tag,
             &format!("{} — {tag}", SETTINGS.site_title),
             &now,
+            &threads,
+            &threads_cache,
+            &mut cache,
         )?;
-        writeln!(File::create(&atom_feed_path)?, "{atom_feed}",)?;
-        interesting_output_paths.insert(atom_feed_path);
-        let threads_content = render_cached_threads_content(&threads_cache, &threads);
-        let threads_page = ThreadsPageTemplate::render(
-            &threads_content,
-            &format!("#{tag} — {}", SETTINGS.site_title),
-            &Some(SITE_PATH_TAGGED.join(&format!("{tag}.feed.xml"))?),
-        )?;
+        interesting_output_paths.extend(feed_paths);
+        let page_title = format!("#{tag} — {}", SETTINGS.site_title);
+        let feed_href = Some(SITE_PATH_TAGGED.join(&format!("{tag}.feed.xml"))?);
         // TODO: move this logic into path module and check for slashes
-        let threads_page_path = SITE_PATH_TAGGED.join(&format!("{tag}.html"))?;
-        writeln!(File::create(&threads_page_path)?, "{threads_page}")?;
-        interesting_output_paths.insert(threads_page_path);
+        let threads_page_paths = write_threads_pages(
+            &SITE_PATH_TAGGED,
+            &tag,
+            &page_title,
+            &feed_href,
+            false,
+            &threads,
+            &threads_cache,
+            &mut cache,
+        )?;
+        interesting_output_paths.extend(threads_page_paths);
+    }
+
+    // generate /tags.html, for the tags in the index collection.
+    let mut index_tags = HashMap::<&str, usize>::default();
+    for thread in collections.threads("index") {
+        for tag in threads_cache[&thread.path].thread.meta.tags.iter() {
+            *index_tags.entry(tag).or_default() += 1;
+        }
     }
+    let tags_page_path = write_tags_page(&index_tags, &mut cache)?;
+    interesting_output_paths.insert(tags_page_path);
 
     let mut tags = tags.into_iter().collect::<Vec<_>>();
     tags.sort_by(|p, q| p.1.cmp(&q.1).reverse().then(p.0.cmp(&q.0)));
@@ -194,13 +298,49 @@ pub fn render(post_paths: Vec<PostsPath>) -> eyre::Result<()> {
             collections.len(key),
         );
         // TODO: write internal collections to another dir?
-        let threads_page_path =
-            collections.write_threads_page(key, &SITE_PATH_ROOT, &threads_cache)?;
+        let threads_page_paths =
+            collections.write_threads_pages(key, &SITE_PATH_ROOT, &threads_cache, &mut cache)?;
         if collections.is_interesting(key) {
-            interesting_output_paths.insert(threads_page_path);
+            interesting_output_paths.extend(threads_page_paths);
+            // author step: generate atom feeds and json feeds.
+            let feed_paths =
+                collections.write_feeds(key, &SITE_PATH_ROOT, &now, &threads_cache, &mut cache)?;
+            interesting_output_paths.extend(feed_paths);
         }
     }
 
+    // generate /archive.html and /archive/<yyyy>.html and /archive/<yyyy>-<mm>.html, for the
+    // threads in the index collection.
+    let archive_paths =
+        write_archive_pages(collections.threads("index"), &threads_cache, &mut cache)?;
+    interesting_output_paths.extend(archive_paths);
+
+    // generate /search.html and /search/*.json, for the threads in the index collection.
+    let search_paths =
+        write_search_index(collections.threads("index"), &threads_cache, &mut cache)?;
+    interesting_output_paths.extend(search_paths);
+
+    // generate /.well-known/webfinger and /activitypub/*.json, for the threads in the index
+    // collection by `[self_author]` or `other_self_authors`.
+    let activitypub_paths =
+        write_activitypub(collections.threads("index"), &threads_cache, &mut cache)?;
+    interesting_output_paths.extend(activitypub_paths);
+
+    // generate /blogroll.html and /blogroll.opml, for the public reader subscriptions.
+    let blogroll_paths = write_blogroll(&mut cache)?;
+    interesting_output_paths.extend(blogroll_paths);
+
+    // generate /sitemap.xml and /robots.txt, for the html pages we are deploying.
+    let sitemap_paths = write_sitemap(&interesting_output_paths, &threads_cache, &mut cache)?;
+    interesting_output_paths.extend(sitemap_paths);
+
+    output_paths.extend(cache.output_paths.iter().cloned());
+
+    // keep cached threads for posts we did not render this time, unless they were deleted.
+    cache.threads.retain(|path, _| path.as_ref().exists());
+    cache.threads.extend(threads_cache);
+    cache.save()?;
+
     let interesting_output_paths = interesting_output_paths
         .into_iter()
         .map(|path| format!("{}\n", path.rsync_deploy_line()))
@@ -210,31 +350,121 @@ pub fn render(post_paths: Vec<PostsPath>) -> eyre::Result<()> {
         File::create(path)?.write_all(interesting_output_paths.as_bytes())?;
     }
 
+    Ok(output_paths)
+}
+
+/// removes the files in site/ that are not in `output_paths`, then any directories in site/ that
+/// are left empty. files in site/attachments/ are hard links to files in attachments/ (see
+/// `run_migrations`), so removing them never touches the attachments store itself.
+fn prune(output_paths: &BTreeSet<SitePath>, dry_run: bool) -> eyre::Result<()> {
+    let mut dirs = vec![SITE_PATH_ROOT.to_owned()];
+    let mut visited_dirs = vec![];
+    let mut stale_paths = vec![];
+    while let Some(dir) = dirs.pop() {
+        for entry in read_dir(&dir)? {
+            let entry = entry?;
+            let path = dir.join_dir_entry(&entry)?;
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if !output_paths.contains(&path) {
+                stale_paths.push(path);
+            }
+        }
+        visited_dirs.push(dir);
+    }
+
+    for path in stale_paths {
+        if let Some(attachments_path) = path.attachments_path()? {
+            // run_migrations should have made sure of this, but let’s be careful.
+            if !attachments_path.as_ref().exists() {
+                warn!(
+                    ?path,
+                    "not pruning attachment that is not in attachments store"
+                );
+                continue;
+            }
+        }
+        if dry_run {
+            info!("would prune: {path:?}");
+        } else {
+            info!("pruning: {path:?}");
+            remove_file(&path)?;
+        }
+    }
+
+    // parents were visited before their children, so this removes children first. keep the
+    // directories that every render creates, even if they are empty.
+    let kept_dirs = [
+        &*SITE_PATH_ROOT,
+        &*SITE_PATH_TAGGED,
+        &*SITE_PATH_SEARCH,
+        &*SITE_PATH_ARCHIVE,
+        &*SITE_PATH_ATTACHMENTS,
+    ];
+    if !dry_run {
+        for dir in visited_dirs.iter().rev() {
+            if !kept_dirs.contains(&dir) && read_dir(dir)?.next().is_none() {
+                info!("pruning empty directory: {dir:?}");
+                remove_dir(dir)?;
+            }
+        }
+    }
+
     Ok(())
 }
 
-fn render_single_post(path: PostsPath) -> eyre::Result<CacheableRenderResult> {
+/// renders the given post, or returns None if the post is unpublished.
+fn render_single_post(
+    path: PostsPath,
+    cached_thread: Option<CachedThread>,
+) -> eyre::Result<Option<CacheableRenderResult>> {
     let mut result = RenderResult::default()?;
 
-    let post = TemplatedPost::load(&path)?;
     let Some(rendered_path) = path.rendered_path()? else {
         bail!("post has no rendered path");
     };
-    let thread = Thread::try_from(post)?;
+    let cached_thread = match cached_thread {
+        Some(cached_thread)
+            if cached_thread.cache_key
+                == CachedThread::cache_key(&path, &cached_thread.thread.meta.references)? =>
+        {
+            trace!(?path, "render cache hit");
+            if !rendered_path.as_ref().exists() {
+                cached_thread.write_page(&rendered_path)?;
+            }
+            cached_thread
+        }
+        _ => {
+            let post = TemplatedPost::load(&path)?;
+            let thread = Thread::try_from(post)?;
+            if thread.meta.is_unpublished {
+                trace!(?path, "skipping unpublished post");
+                return Ok(None);
+            }
+            let cached_thread = CachedThread {
+                cache_key: CachedThread::cache_key(&path, &thread.meta.references)?,
+                threads_content_normal: ThreadsContentTemplate::render_normal(&thread)?,
+                threads_content_simple: ThreadsContentTemplate::render_simple(&thread)?,
+                thread,
+            };
+            cached_thread.write_page(&rendered_path)?;
+            cached_thread
+        }
+    };
+    let thread = &cached_thread.thread;
+
     hard_link_attachments_into_site(thread.needs_attachments())?;
     for tag in thread.meta.tags.iter() {
         *result.tags.entry(tag.clone()).or_insert(0usize) += 1;
     }
-    result.collections.push("all", &path, &thread);
+    result.collections.push("all", &path, thread);
     let mut was_interesting = false;
     if thread.meta.is_main_self_author(&SETTINGS) {
         was_interesting = true;
-    } else if SETTINGS.thread_is_on_excluded_archived_list(&thread) {
-        result.collections.push("excluded", &path, &thread);
-    } else if SETTINGS.thread_is_on_interesting_archived_list(&thread) {
-        result
-            .collections
-            .push("marked_interesting", &path, &thread);
+    } else if SETTINGS.thread_is_on_excluded_archived_list(thread) {
+        result.collections.push("excluded", &path, thread);
+    } else if SETTINGS.thread_is_on_interesting_archived_list(thread) {
+        result.collections.push("marked_interesting", &path, thread);
         was_interesting = true;
     } else if thread.meta.is_any_self_author(&SETTINGS) {
         for tag in thread.meta.tags.iter() {
@@ -248,11 +478,11 @@ fn render_single_post(path: PostsPath) -> eyre::Result<CacheableRenderResult> {
         result
             .interesting_output_paths
             .insert(rendered_path.clone());
-        result.collections.push("index", &path, &thread);
+        result.collections.push("index", &path, thread);
         for tag in thread.meta.tags.iter() {
-            if SETTINGS.tag_is_interesting(tag) {
+            if SETTINGS.tag_has_page(tag) {
                 result
-                    .threads_by_interesting_tag
+                    .threads_by_tag
                     .entry(tag.clone())
                     .or_default()
                     .insert(ThreadInCollection {
@@ -264,7 +494,7 @@ fn render_single_post(path: PostsPath) -> eyre::Result<CacheableRenderResult> {
         if thread.meta.tags.is_empty() {
             result
                 .collections
-                .push("untagged_interesting", &path, &thread);
+                .push("untagged_interesting", &path, thread);
         }
     } else if let Some(last_post) = thread.posts.last() {
         // at this point, if the last post was ours, it was one of our archived chosts or rechosts.
@@ -273,39 +503,36 @@ fn render_single_post(path: PostsPath) -> eyre::Result<CacheableRenderResult> {
             // if the thread had some input from us at publish time, that is, if the last post was
             // authored by us with content and/or tags...
             if !last_post.meta.is_transparent_share || !last_post.meta.tags.is_empty() {
-                result.collections.push("skipped_own", &path, &thread);
+                result.collections.push("skipped_own", &path, thread);
             } else {
-                result.collections.push("skipped_other", &path, &thread);
+                result.collections.push("skipped_other", &path, thread);
             }
         } else {
             // liked chosts are generally non-“interesting” archived chosts where the last post was
             // not authored by us. unfortunately this does not include liking our own chosts :(
-            result.collections.push("liked", &path, &thread);
+            result.collections.push("liked", &path, thread);
         }
     }
 
-    let threads_content_normal = ThreadsContentTemplate::render_normal(&thread)?;
-    let threads_content_simple = ThreadsContentTemplate::render_simple(&thread)?;
-
-    debug!("writing post page: {rendered_path:?}");
-    let threads_page = ThreadsPageTemplate::render_single_thread(
-        &thread,
-        &threads_content_normal,
-        &SETTINGS.page_title(thread.meta.title.as_deref()),
-        &None,
-    )?;
-    writeln!(File::create(rendered_path)?, "{threads_page}")?;
+    // user-defined collections from the settings. threads in collections with feeds are
+    // “interesting”, so their pages are deployed along with the collection page.
+    for collection in SETTINGS.collections() {
+        if collection.filter.matches(&thread.meta, &SETTINGS) {
+            result.collections.push(&collection.filename, &path, thread);
+            if collection.feed == Some(true) {
+                result
+                    .interesting_output_paths
+                    .insert(rendered_path.clone());
+            }
+        }
+    }
 
     let result = CacheableRenderResult {
         render_result: result,
-        cached_thread: CachedThread {
-            thread,
-            threads_content_normal,
-            threads_content_simple,
-        },
+        cached_thread,
     };
 
-    Ok(result)
+    Ok(Some(result))
 }
 
 struct CacheableRenderResult {
@@ -317,27 +544,46 @@ struct RenderResult {
     tags: HashMap<String, usize>,
     collections: Collections,
     interesting_output_paths: BTreeSet<SitePath>,
-    threads_by_interesting_tag: HashMap<String, BTreeSet<ThreadInCollection>>,
+    threads_by_tag: HashMap<String, BTreeSet<ThreadInCollection>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct CachedThread {
     pub thread: Thread,
     pub threads_content_normal: String,
     pub threads_content_simple: String,
+    /// hash of the post and the posts it references, at the time we rendered the thread.
+    pub cache_key: String,
+}
+
+/// persistent cache of rendered threads and pages, so we can skip the work of rendering any
+/// threads and pages that have not changed since the last render.
+#[derive(Default, Deserialize, Serialize)]
+struct RenderCache {
+    /// hash of the settings and autost version. if this changes, the whole cache is stale.
+    settings_key: String,
+    threads: BTreeMap<PostsPath, CachedThread>,
+    pages: BTreeMap<SitePath, String>,
+    /// newest `published` of the threads on each threads page in this render, for sitemap.xml.
+    #[serde(skip)]
+    newest_published: BTreeMap<SitePath, String>,
+    /// pages written (or skipped because they were fresh) in this render, for `render --prune`.
+    #[serde(skip)]
+    output_paths: BTreeSet<SitePath>,
 }
 
 struct Collections {
-    inner: BTreeMap<&'static str, Collection>,
+    inner: BTreeMap<String, Collection>,
 }
 
 struct Collection {
     feed_href: Option<SitePath>,
     title: String,
+    feed_title: String,
     threads: BTreeSet<ThreadInCollection>,
 }
 
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Eq, PartialEq)]
 struct ThreadInCollection {
     published: Option<String>,
     path: PostsPath,
@@ -349,57 +595,173 @@ impl RenderResult {
             tags: Default::default(),
             collections: Collections::default()?,
             interesting_output_paths: Default::default(),
-            threads_by_interesting_tag: Default::default(),
+            threads_by_tag: Default::default(),
         })
     }
 }
 
+impl CachedThread {
+    fn cache_key(path: &PostsPath, references: &[PostsPath]) -> eyre::Result<String> {
+        let mut parts = vec![std::fs::read(path)?];
+        for path in references {
+            parts.push(path.references_url().into_bytes());
+            parts.push(std::fs::read(path)?);
+        }
+        match std::fs::read(path.webmentions_path()?) {
+            Ok(webmentions) => parts.push(webmentions),
+            Err(error) if error.kind() == ErrorKind::NotFound => parts.push(vec![]),
+            Err(other) => Err(other)?,
+        }
+
+        Ok(cache_key(parts))
+    }
+
+    fn write_page(&self, rendered_path: &SitePath) -> eyre::Result<()> {
+        debug!("writing post page: {rendered_path:?}");
+        let threads_page = ThreadsPageTemplate::render_single_thread(
+            &self.thread,
+            &self.threads_content_normal,
+            &SETTINGS.page_title(self.thread.meta.title.as_deref()),
+            &None,
+        )?;
+        writeln!(File::create(rendered_path)?, "{threads_page}")?;
+
+        Ok(())
+    }
+}
+
+impl RenderCache {
+    fn load() -> eyre::Result<Self> {
+        let settings_key = Self::settings_key()?;
+        let result = match File::open(&*CACHE_PATH_RENDER) {
+            Ok(file) => match serde_json::from_reader::<_, Self>(BufReader::new(file)) {
+                Ok(cache) if cache.settings_key == settings_key => Some(cache),
+                Ok(_) => {
+                    info!("settings or autost version changed, rendering everything");
+                    None
+                }
+                Err(error) => {
+                    warn!(?error, "failed to read render cache, rendering everything");
+                    None
+                }
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(other) => Err(other)?,
+        };
+
+        Ok(result.unwrap_or(Self {
+            settings_key,
+            ..Default::default()
+        }))
+    }
+
+    fn save(&self) -> eyre::Result<()> {
+        create_dir_all(&*CACHE_PATH_ROOT)?;
+        let temp_path = CACHE_PATH_ROOT.join("render.json.tmp")?;
+        let mut file = BufWriter::new(File::create(&temp_path)?);
+        serde_json::to_writer(&mut file, self)?;
+        file.flush()?;
+        drop(file);
+        rename(temp_path, &*CACHE_PATH_RENDER)?;
+
+        Ok(())
+    }
+
+    fn settings_key() -> eyre::Result<String> {
+        let version = RunDetails::default().version;
+        let settings = serde_json::to_string(&*SETTINGS)?;
+
+        Ok(cache_key([version, settings]))
+    }
+
+    /// writes the output of `render` to the page at `path`, unless the page exists and
+    /// `cache_key` is the same as it was when we last wrote the page.
+    fn write_page(
+        &mut self,
+        path: &SitePath,
+        cache_key: String,
+        render: impl FnOnce() -> eyre::Result<String>,
+    ) -> eyre::Result<()> {
+        self.output_paths.insert(path.clone());
+        if self.pages.get(path) == Some(&cache_key) && path.as_ref().exists() {
+            trace!(?path, "render cache hit");
+            return Ok(());
+        }
+        debug!(?path, "writing page");
+        writeln!(File::create(path)?, "{}", render()?)?;
+        self.pages.insert(path.clone(), cache_key);
+
+        Ok(())
+    }
+}
+
 impl Collections {
     fn default() -> eyre::Result<Self> {
-        Ok(Self {
-            inner: [
-                (
-                    "index",
-                    Collection::new(Some(SITE_PATH_ROOT.join("index.feed.xml")?), "posts"),
-                ),
-                ("all", Collection::new(None, "all posts")),
-                (
-                    "untagged_interesting",
-                    Collection::new(None, "untagged interesting posts"),
-                ),
-                (
-                    "excluded",
-                    Collection::new(None, "archived posts that were marked excluded"),
-                ),
-                (
-                    "marked_interesting",
-                    Collection::new(None, "archived posts that were marked interesting"),
-                ),
-                (
-                    "skipped_own",
-                    Collection::new(None, "own skipped archived posts"),
-                ),
-                (
-                    "skipped_other",
-                    Collection::new(None, "others’ skipped archived posts"),
-                ),
-                (
-                    "liked",
-                    Collection::new(None, "liked chosts (except liking your own chosts)"),
-                ),
-            ]
-            .into(),
-        })
+        let mut inner = [
+            (
+                "index",
+                Collection {
+                    feed_title: SETTINGS.site_title.clone(),
+                    ..Collection::new(Some(SITE_PATH_ROOT.join("index.feed.xml")?), "posts")
+                },
+            ),
+            ("all", Collection::new(None, "all posts")),
+            (
+                "untagged_interesting",
+                Collection::new(None, "untagged interesting posts"),
+            ),
+            (
+                "excluded",
+                Collection::new(None, "archived posts that were marked excluded"),
+            ),
+            (
+                "marked_interesting",
+                Collection::new(None, "archived posts that were marked interesting"),
+            ),
+            (
+                "skipped_own",
+                Collection::new(None, "own skipped archived posts"),
+            ),
+            (
+                "skipped_other",
+                Collection::new(None, "others’ skipped archived posts"),
+            ),
+            (
+                "liked",
+                Collection::new(None, "liked chosts (except liking your own chosts)"),
+            ),
+        ]
+        .map(|(key, collection)| (key.to_owned(), collection))
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+
+        for collection in SETTINGS.collections() {
+            let key = &collection.filename;
+            // these are pages written by `render`, but not collections.
+            if ["archive", "blogroll", "search", "tags"].contains(&&**key)
+                || inner.contains_key(key)
+            {
+                bail!("collection filename is already used: {key:?}");
+            }
+            let feed_href = if collection.feed == Some(true) {
+                Some(SITE_PATH_ROOT.join(&format!("{key}.feed.xml"))?)
+            } else {
+                None
+            };
+            inner.insert(key.clone(), Collection::new(feed_href, &collection.title));
+        }
+
+        Ok(Self { inner })
     }
 
     fn merge(&mut self, other: Self) {
         assert!(self.inner.keys().eq(other.inner.keys()));
         for (key, collection) in other.inner {
-            assert_eq!(self.inner[key].feed_href, collection.feed_href);
-            assert_eq!(self.inner[key].title, collection.title);
+            assert_eq!(self.inner[&key].feed_href, collection.feed_href);
+            assert_eq!(self.inner[&key].title, collection.title);
             let threads = &mut self
                 .inner
-                .get_mut(key)
+                .get_mut(&key)
                 .expect("guaranteed by assert")
                 .threads;
             for thread in collection.threads {
@@ -409,7 +771,11 @@ impl Collections {
     }
 
     fn keys(&self) -> impl Iterator<Item = &str> {
-        self.inner.keys().copied()
+        self.inner.keys().map(|key| &**key)
+    }
+
+    fn threads(&self, key: &str) -> impl Iterator<Item = &ThreadInCollection> {
+        self.inner[key].threads.iter()
     }
 
     fn len(&self, key: &str) -> usize {
@@ -431,29 +797,25 @@ impl Collections {
         self.inner[key].is_interesting()
     }
 
-    fn write_threads_page(
+    fn write_threads_pages(
         &self,
         key: &str,
         output_dir: &SitePath,
         threads_cache: &HashMap<PostsPath, CachedThread>,
-    ) -> eyre::Result<SitePath> {
-        let path = output_dir.join(&format!("{key}.html"))?;
-        self.inner[key].write_threads_page(&path, threads_cache)?;
-
-        Ok(path)
+        cache: &mut RenderCache,
+    ) -> eyre::Result<Vec<SitePath>> {
+        self.inner[key].write_threads_pages(key, output_dir, threads_cache, cache)
     }
 
-    fn write_atom_feed(
+    fn write_feeds(
         &self,
         key: &str,
         output_dir: &SitePath,
         now: &str,
         threads_cache: &HashMap<PostsPath, CachedThread>,
-    ) -> eyre::Result<SitePath> {
-        let path = output_dir.join(&format!("{key}.feed.xml"))?;
-        self.inner[key].write_atom_feed(&path, now, threads_cache)?;
-
-        Ok(path)
+        cache: &mut RenderCache,
+    ) -> eyre::Result<Vec<SitePath>> {
+        self.inner[key].write_feeds(key, output_dir, now, threads_cache, cache)
     }
 }
 
@@ -462,6 +824,7 @@ impl Collection {
         Self {
             feed_href,
             title: title.to_owned(),
+            feed_title: format!("{} — {title}", SETTINGS.site_title),
             threads: BTreeSet::default(),
         }
     }
@@ -471,43 +834,45 @@ impl Collection {
         self.feed_href.is_some()
     }
 
-    fn write_threads_page(
+    fn write_threads_pages(
         &self,
-        posts_page_path: &SitePath,
+        key: &str,
+        output_dir: &SitePath,
         threads_cache: &HashMap<PostsPath, CachedThread>,
-    ) -> eyre::Result<()> {
-        let threads_content = render_cached_threads_content(threads_cache, &self.threads);
-        writeln!(
-            File::create(posts_page_path)?,
-            "{}",
-            ThreadsPageTemplate::render(
-                &threads_content,
-                &format!("{} — {}", self.title, SETTINGS.site_title),
-                &self.feed_href,
-            )?
-        )?;
-
-        Ok(())
+        cache: &mut RenderCache,
+    ) -> eyre::Result<Vec<SitePath>> {
+        // pages for collections that are not “interesting” are not deployed by deploy.sh, but
+        // they may be deployed some other way, so optionally ask search engines not to index them.
+        let noindex = !self.is_interesting() && SETTINGS.noindex_uninteresting_collections();
+        write_threads_pages(
+            output_dir,
+            key,
+            &format!("{} — {}", self.title, SETTINGS.site_title),
+            &self.feed_href,
+            noindex,
+            &self.threads,
+            threads_cache,
+            cache,
+        )
     }
 
-    fn write_atom_feed(
+    fn write_feeds(
         &self,
-        atom_feed_path: &SitePath,
+        key: &str,
+        output_dir: &SitePath,
         now: &str,
         threads_cache: &HashMap<PostsPath, CachedThread>,
-    ) -> eyre::Result<()> {
-        let cached_threads = self
-            .threads
-            .iter()
-            .map(|thread| &threads_cache[&thread.path])
-            .collect::<Vec<_>>();
-        writeln!(
-            File::create(atom_feed_path)?,
-            "{}",
-            AtomFeedTemplate::render(cached_threads, &SETTINGS.site_title, now)?
-        )?;
-
-        Ok(())
+        cache: &mut RenderCache,
+    ) -> eyre::Result<Vec<SitePath>> {
+        write_feeds(
+            output_dir,
+            key,
+            &self.feed_title,
+            now,
+            &self.threads,
+            threads_cache,
+            cache,
+        )
     }
 }
 
@@ -526,9 +891,501 @@ impl PartialOrd for ThreadInCollection {
     }
 }
 
+/// writes the given threads to `<stem>.html`, or if there are more than `threads_page_size`,
+/// splits them into `<stem>.html`, `<stem>.2.html`, `<stem>.3.html`, and so on.
+#[allow(clippy::too_many_arguments)]
+fn write_threads_pages(
+    output_dir: &SitePath,
+    stem: &str,
+    page_title: &str,
+    feed_href: &Option<SitePath>,
+    noindex: bool,
+    threads: &BTreeSet<ThreadInCollection>,
+    threads_cache: &HashMap<PostsPath, CachedThread>,
+    cache: &mut RenderCache,
+) -> eyre::Result<Vec<SitePath>> {
+    let threads = threads.iter().collect::<Vec<_>>();
+    let pages = match SETTINGS.threads_page_size {
+        Some(page_size) if !threads.is_empty() => threads.chunks(page_size).collect(),
+        _ => vec![&threads[..]],
+    };
+    let page_paths = (1..=pages.len())
+        .map(|page| threads_page_path(output_dir, stem, page))
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let feed_url = feed_href.as_ref().map(|href| href.internal_url());
+
+    for (i, threads) in pages.iter().enumerate() {
+        let pagination = (pages.len() > 1).then(|| Pagination {
+            page: i + 1,
+            page_count: pages.len(),
+            prev_href: i.checked_sub(1).map(|j| page_paths[j].clone()),
+            next_href: page_paths.get(i + 1).cloned(),
+        });
+        let page_number = (i + 1).to_string();
+        let page_count = pages.len().to_string();
+        let cache_key = threads_cache_key(
+            [
+                "threads page",
+                page_title,
+                feed_url.as_deref().unwrap_or(""),
+                &*page_number,
+                &*page_count,
+                if noindex { "noindex" } else { "" },
+            ],
+            threads_cache,
+            threads.iter().copied(),
+        );
+        cache.write_page(&page_paths[i], cache_key, || {
+            let threads_content = render_cached_threads_content(threads_cache, threads);
+            ThreadsPageTemplate::render(
+                &threads_content,
+                page_title,
+                feed_href,
+                pagination.as_ref(),
+                noindex,
+            )
+        })?;
+        if let Some(published) = threads.iter().find_map(|thread| thread.published.as_ref()) {
+            cache
+                .newest_published
+                .insert(page_paths[i].clone(), published.clone());
+        }
+    }
+
+    Ok(page_paths)
+}
+
+/// writes the newest `atom_feed_max_entries` threads to `<stem>.feed.xml` and `<stem>.feed.json`, and if there are more
+/// threads than that, writes all of the threads to archived feeds `<stem>.archive.1.feed.xml`,
+/// `<stem>.archive.2.feed.xml`, and so on, oldest first (RFC 5005).
+///
+/// each archived feed has exactly `atom_feed_max_entries` threads, so the contents of an archived
+/// feed never change unless older threads change. the threads that do not fill an archived feed
+/// are only in the subscription feed.
+fn write_feeds(
+    output_dir: &SitePath,
+    stem: &str,
+    feed_title: &str,
+    now: &str,
+    threads: &BTreeSet<ThreadInCollection>,
+    threads_cache: &HashMap<PostsPath, CachedThread>,
+    cache: &mut RenderCache,
+) -> eyre::Result<Vec<SitePath>> {
+    // newest first.
+    let threads = threads.iter().collect::<Vec<_>>();
+    let max_entries = SETTINGS.atom_feed_max_entries.unwrap_or(usize::MAX);
+    let archive_count = if threads.len() > max_entries {
+        threads.len() / max_entries
+    } else {
+        0
+    };
+    let feed_path = output_dir.join(&format!("{stem}.feed.xml"))?;
+    let archive_paths = (1..=archive_count)
+        .map(|i| output_dir.join(&format!("{stem}.archive.{i}.feed.xml")))
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    let mut write_feed = |path: &SitePath,
+                          threads: &[&ThreadInCollection],
+                          links: AtomFeedLinks|
+     -> eyre::Result<()> {
+        let links_key = [
+            &links.current_href,
+            &links.prev_archive_href,
+            &links.next_archive_href,
+        ]
+        .map(|href| {
+            href.as_ref()
+                .map(|href| href.external_url())
+                .unwrap_or_default()
+        });
+        let cache_key = threads_cache_key(
+            ["atom feed", feed_title]
+                .into_iter()
+                .chain(links_key.iter().map(|href| &**href)),
+            threads_cache,
+            threads.iter().copied(),
+        );
+        cache.write_page(path, cache_key, || {
+            let cached_threads = threads
+                .iter()
+                .map(|thread| &threads_cache[&thread.path])
+                .collect::<Vec<_>>();
+            AtomFeedTemplate::render(cached_threads, feed_title, now, &links)
+        })
+    };
+
+    let newest = &threads[..threads.len().min(max_entries)];
+    write_feed(
+        &feed_path,
+        newest,
+        AtomFeedLinks {
+            self_href: feed_path.clone(),
+            current_href: None,
+            prev_archive_href: archive_paths.last().cloned(),
+            next_archive_href: None,
+        },
+    )?;
+    for (i, archive_path) in archive_paths.iter().enumerate() {
+        let end = threads.len() - i * max_entries;
+        let threads = &threads[(end - max_entries)..end];
+        write_feed(
+            archive_path,
+            threads,
+            AtomFeedLinks {
+                self_href: archive_path.clone(),
+                current_href: Some(feed_path.clone()),
+                prev_archive_href: i.checked_sub(1).map(|j| archive_paths[j].clone()),
+                next_archive_href: archive_paths.get(i + 1).cloned(),
+            },
+        )?;
+    }
+
+    let json_feed_path = output_dir.join(&format!("{stem}.feed.json"))?;
+    let cache_key = threads_cache_key(
+        ["json feed", feed_title],
+        threads_cache,
+        newest.iter().copied(),
+    );
+    cache.write_page(&json_feed_path, cache_key, || {
+        let cached_threads = newest
+            .iter()
+            .map(|thread| &threads_cache[&thread.path])
+            .collect::<Vec<_>>();
+        JsonFeed::render(cached_threads, feed_title, &json_feed_path)
+    })?;
+
+    Ok([feed_path, json_feed_path]
+        .into_iter()
+        .chain(archive_paths)
+        .collect())
+}
+
+fn write_tags_page(tags: &HashMap<&str, usize>, cache: &mut RenderCache) -> eyre::Result<SitePath> {
+    let interesting_groups = SETTINGS
+        .interesting_tag_groups_iter()
+        .map(|group| {
+            group
+                .iter()
+                .filter_map(|tag| Some((&**tag, *tags.get(&**tag)?)))
+                .collect::<Vec<_>>()
+        })
+        .filter(|group| !group.is_empty())
+        .collect::<Vec<_>>();
+    let mut other_tags = tags
+        .iter()
+        .filter(|(tag, _)| !SETTINGS.tag_is_interesting(tag))
+        .map(|(tag, count)| (*tag, *count))
+        .collect::<Vec<_>>();
+    other_tags.sort_by(|p, q| p.1.cmp(&q.1).reverse().then(p.0.cmp(q.0)));
+
+    let path = SITE_PATH_ROOT.join("tags.html")?;
+    let content = ThreadsPageTemplate::render(
+        &TagsDirectoryTemplate::render(&interesting_groups, &other_tags)?,
+        &SETTINGS.page_title(Some("tags")),
+        &None,
+        None,
+        false,
+    )?;
+    cache.write_page(&path, cache_key([&content]), || Ok(content))?;
+
+    Ok(path)
+}
+
+fn write_archive_pages<'threads>(
+    threads: impl Iterator<Item = &'threads ThreadInCollection>,
+    threads_cache: &HashMap<PostsPath, CachedThread>,
+    cache: &mut RenderCache,
+) -> eyre::Result<Vec<SitePath>> {
+    let mut years = BTreeMap::<&str, BTreeSet<ThreadInCollection>>::default();
+    let mut months = BTreeMap::<&str, BTreeSet<ThreadInCollection>>::default();
+    for thread in threads {
+        // threads with no published date, or a published date we don’t understand, are skipped.
+        let Some(month) = thread
+            .published
+            .as_deref()
+            .and_then(|published| published.get(..7))
+            .filter(|month| {
+                month.char_indices().all(|(i, c)| match i {
+                    4 => c == '-',
+                    _ => c.is_ascii_digit(),
+                })
+            })
+        else {
+            continue;
+        };
+        years.entry(&month[..4]).or_default().insert(thread.clone());
+        months.entry(month).or_default().insert(thread.clone());
+    }
+
+    let mut result = vec![];
+    for (period, threads) in years.iter().chain(months.iter()) {
+        let threads_page_paths = write_threads_pages(
+            &SITE_PATH_ARCHIVE,
+            period,
+            &SETTINGS.page_title(Some(period)),
+            &None,
+            false,
+            threads,
+            threads_cache,
+            cache,
+        )?;
+        result.extend(threads_page_paths);
+    }
+
+    let years = years
+        .iter()
+        .rev()
+        .map(|(year, threads)| ArchiveYear {
+            year,
+            count: threads.len(),
+            months: months
+                .iter()
+                .rev()
+                .filter(|(month, _)| month.starts_with(year))
+                .map(|(month, threads)| (*month, threads.len()))
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+    let path = SITE_PATH_ROOT.join("archive.html")?;
+    let content = ThreadsPageTemplate::render(
+        &ArchiveOverviewTemplate::render(&years)?,
+        &SETTINGS.page_title(Some("archive")),
+        &None,
+        None,
+        false,
+    )?;
+    cache.write_page(&path, cache_key([&content]), || Ok(content))?;
+    result.push(path);
+
+    Ok(result)
+}
+
+/// writes `sitemap.xml`, or if there are too many pages for one sitemap, a sitemap index that
+/// points to `sitemap.1.xml`, `sitemap.2.xml`, and so on. also writes `robots.txt`.
+fn write_sitemap(
+    interesting_output_paths: &BTreeSet<SitePath>,
+    threads_cache: &HashMap<PostsPath, CachedThread>,
+    cache: &mut RenderCache,
+) -> eyre::Result<Vec<SitePath>> {
+    // <https://www.sitemaps.org/protocol.html#index>
+    const MAX_URLS_PER_SITEMAP: usize = 50_000;
+
+    let mut newest_published = cache.newest_published.clone();
+    for cached_thread in threads_cache.values() {
+        let thread = &cached_thread.thread;
+        let rendered_path = thread
+            .path
+            .as_ref()
+            .map(|path| path.rendered_path())
+            .transpose()?
+            .flatten();
+        // the page of an edited thread was last modified when it was updated, not published.
+        let lastmod = thread
+            .meta
+            .updated
+            .as_ref()
+            .or(thread.meta.published.as_ref());
+        if let Some((path, lastmod)) = rendered_path.zip(lastmod) {
+            newest_published.insert(path, lastmod.clone());
+        }
+    }
+
+    let entries = interesting_output_paths
+        .iter()
+        .filter(|path| path.as_ref().extension().is_some_and(|ext| ext == "html"))
+        .map(|path| SitemapEntry {
+            loc: path.external_url(),
+            lastmod: newest_published.get(path).cloned(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut result = vec![];
+    let sitemap_path = SITE_PATH_ROOT.join("sitemap.xml")?;
+    let chunks = entries.chunks(MAX_URLS_PER_SITEMAP).collect::<Vec<_>>();
+    if chunks.len() > 1 {
+        let mut sitemap_entries = vec![];
+        for (i, entries) in chunks.into_iter().enumerate() {
+            let path = SITE_PATH_ROOT.join(&format!("sitemap.{}.xml", i + 1))?;
+            let content = SitemapTemplate::render(entries)?;
+            cache.write_page(&path, cache_key([&content]), || Ok(content))?;
+            sitemap_entries.push(SitemapEntry {
+                loc: path.external_url(),
+                lastmod: entries
+                    .iter()
+                    .filter_map(|entry| entry.lastmod.clone())
+                    .max(),
+            });
+            result.push(path);
+        }
+        let content = SitemapIndexTemplate::render(&sitemap_entries)?;
+        cache.write_page(&sitemap_path, cache_key([&content]), || Ok(content))?;
+    } else {
+        let content = SitemapTemplate::render(&entries)?;
+        cache.write_page(&sitemap_path, cache_key([&content]), || Ok(content))?;
+    }
+
+    let robots_path = SITE_PATH_ROOT.join("robots.txt")?;
+    let content = format!(
+        "User-agent: *\nAllow: /\nSitemap: {}",
+        sitemap_path.external_url()
+    );
+    cache.write_page(&robots_path, cache_key([&content]), || Ok(content))?;
+
+    result.push(sitemap_path);
+    result.push(robots_path);
+
+    Ok(result)
+}
+
+fn write_search_index<'threads>(
+    threads: impl Iterator<Item = &'threads ThreadInCollection>,
+    threads_cache: &HashMap<PostsPath, CachedThread>,
+    cache: &mut RenderCache,
+) -> eyre::Result<Vec<SitePath>> {
+    let mut index = SearchIndex::default();
+    for thread in threads {
+        let rendered_path = thread
+            .path
+            .rendered_path()?
+            .ok_or_eyre("post has no rendered path")?;
+        index.push(
+            rendered_path.internal_url(),
+            &threads_cache[&thread.path].thread,
+        );
+    }
+
+    let mut result = vec![];
+    for (filename, content) in index.files()? {
+        let path = SITE_PATH_SEARCH.join(&filename)?;
+        cache.write_page(&path, cache_key([&content]), || Ok(content))?;
+        result.push(path);
+    }
+    let search_page_path = SITE_PATH_ROOT.join("search.html")?;
+    cache.write_page(&search_page_path, cache_key(["search page"]), || {
+        ThreadsPageTemplate::render(
+            &SearchFormTemplate::render()?,
+            &SETTINGS.page_title(Some("search")),
+            &None,
+            None,
+            false,
+        )
+    })?;
+    result.push(search_page_path);
+
+    Ok(result)
+}
+
+/// writes nothing unless the `blogroll` setting is true.
+fn write_blogroll(cache: &mut RenderCache) -> eyre::Result<Vec<SitePath>> {
+    if SETTINGS.blogroll != Some(true) {
+        return Ok(vec![]);
+    }
+    let subscriptions = Subscriptions::load()?;
+    let public_subscriptions = subscriptions
+        .subscriptions
+        .iter()
+        .filter(|subscription| subscription.public)
+        .collect::<Vec<_>>();
+
+    let mut result = vec![];
+    let path = SITE_PATH_ROOT.join("blogroll.html")?;
+    let content = ThreadsPageTemplate::render(
+        &BlogrollTemplate::render(&public_subscriptions)?,
+        &SETTINGS.page_title(Some("blogroll")),
+        &None,
+        None,
+        false,
+    )?;
+    cache.write_page(&path, cache_key([&content]), || Ok(content))?;
+    result.push(path);
+    let path = SITE_PATH_ROOT.join("blogroll.opml")?;
+    let content = OpmlTemplate::render(
+        &SETTINGS.page_title(Some("blogroll")),
+        &public_subscriptions,
+    )?;
+    cache.write_page(&path, cache_key([&content]), || Ok(content))?;
+    result.push(path);
+
+    Ok(result)
+}
+
+/// writes nothing if `[self_author]` is not set, since there would be no one to be the actor.
+fn write_activitypub<'threads>(
+    threads: impl Iterator<Item = &'threads ThreadInCollection>,
+    threads_cache: &HashMap<PostsPath, CachedThread>,
+    cache: &mut RenderCache,
+) -> eyre::Result<Vec<SitePath>> {
+    let Some(author) = &SETTINGS.self_author else {
+        return Ok(vec![]);
+    };
+    create_dir_all(&*SITE_PATH_WELL_KNOWN)?;
+    create_dir_all(&*SITE_PATH_ACTIVITYPUB)?;
+
+    // threads by anyone else can’t be attributed to our actor.
+    let threads = threads
+        .filter(|thread| {
+            threads_cache[&thread.path]
+                .thread
+                .meta
+                .is_any_self_author(&SETTINGS)
+        })
+        .collect::<Vec<_>>();
+    let pages = threads.chunks(OUTBOX_PAGE_SIZE).collect::<Vec<_>>();
+
+    let mut result = vec![];
+    let path = webfinger_path()?;
+    cache.write_page(&path, cache_key(["activitypub webfinger"]), || {
+        render_webfinger(author)
+    })?;
+    result.push(path);
+    let path = actor_path()?;
+    cache.write_page(&path, cache_key(["activitypub actor"]), || {
+        render_actor(author)
+    })?;
+    result.push(path);
+    let path = inbox_path()?;
+    cache.write_page(&path, cache_key(["activitypub inbox"]), render_inbox)?;
+    result.push(path);
+    let path = outbox_path()?;
+    let content = render_outbox(threads.len(), pages.len())?;
+    cache.write_page(&path, cache_key([&content]), || Ok(content))?;
+    result.push(path);
+
+    for (i, threads) in pages.iter().enumerate() {
+        let path = outbox_page_path(i + 1)?;
+        let page_number = (i + 1).to_string();
+        let page_count = pages.len().to_string();
+        let cache_key = threads_cache_key(
+            ["activitypub outbox page", &*page_number, &*page_count],
+            threads_cache,
+            threads.iter().copied(),
+        );
+        cache.write_page(&path, cache_key, || {
+            render_outbox_page(
+                threads
+                    .iter()
+                    .map(|thread| &threads_cache[&thread.path].thread),
+                i + 1,
+                pages.len(),
+            )
+        })?;
+        result.push(path);
+    }
+
+    Ok(result)
+}
+
+fn threads_page_path(output_dir: &SitePath, stem: &str, page: usize) -> eyre::Result<SitePath> {
+    if page == 1 {
+        output_dir.join(&format!("{stem}.html"))
+    } else {
+        output_dir.join(&format!("{stem}.{page}.html"))
+    }
+}
+
 fn render_cached_threads_content(
     cache: &HashMap<PostsPath, CachedThread>,
-    threads: &BTreeSet<ThreadInCollection>,
+    threads: &[&ThreadInCollection],
 ) -> String {
     let threads_contents = threads
         .iter()
@@ -537,3 +1394,32 @@ fn render_cached_threads_content(
 
     threads_contents.join("")
 }
+
+/// hashes the given parts, in a way that does not let parts run into each other.
+fn cache_key(parts: impl IntoIterator<Item = impl AsRef<[u8]>>) -> String {
+    let mut hash = Sha256::new();
+    for part in parts {
+        let part = part.as_ref();
+        hash.update(part.len().to_le_bytes());
+        hash.update(part);
+    }
+
+    format!("{:x}", hash.finalize())
+}
+
+/// cache key for a page or feed containing the given threads, in the given order.
+fn threads_cache_key<'threads>(
+    parts: impl IntoIterator<Item = &'threads str>,
+    cache: &'threads HashMap<PostsPath, CachedThread>,
+    threads: impl Iterator<Item = &'threads ThreadInCollection>,
+) -> String {
+    let threads = threads.map(|thread| &*cache[&thread.path].cache_key);
+
+    cache_key(parts.into_iter().chain(threads))
+}
+
+#[test]
+fn test_cache_key() {
+    assert_ne!(cache_key(["ab", "c"]), cache_key(["a", "bc"]));
+    assert_eq!(cache_key(["a", "bc"]), cache_key([&b"a"[..], &b"bc"[..]]));
+}
@@ -0,0 +1,35 @@
+use clap::Parser as _;
+use jane_eyre::eyre;
+
+use crate::{
+    command::render::all_post_paths, path::PostsPath, webmention::send_webmentions, Command,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct SendWebmentions {
+    specific_post_paths: Vec<String>,
+
+    #[arg(
+        long,
+        help = "find webmention endpoints, but only log the webmentions that would be sent"
+    )]
+    dry_run: bool,
+}
+
+/// sends webmentions for the given posts, or all posts, even if `send_webmentions` is not enabled.
+#[tokio::main]
+pub async fn main() -> eyre::Result<()> {
+    let Command::SendWebmentions(args) = Command::parse() else {
+        unreachable!("guaranteed by subcommand call in entry point")
+    };
+    let post_paths = if args.specific_post_paths.is_empty() {
+        all_post_paths()?
+    } else {
+        args.specific_post_paths
+            .iter()
+            .map(|path| PostsPath::from_site_root_relative_path(path))
+            .collect::<eyre::Result<Vec<_>>>()?
+    };
+
+    send_webmentions(&post_paths, args.dry_run).await
+}
@@ -1,41 +1,78 @@
 use std::{
-    fs::File,
-    io::{self, Write as _},
+    env,
+    fs::{create_dir_all, read_to_string, remove_dir_all},
+    thread,
+    time::Duration,
 };
 
 use crate::{
-    command::render::render_all,
+    attachments::{AttachmentsContext as _, RealAttachmentsContext},
+    auth::{Sessions, User, SESSION_COOKIE, SESSION_LIFETIME},
+    command::{delete::delete, render::render_all, unpublish::unpublish},
+    micropub,
     output::ThreadsContentTemplate,
-    path::{PostsPath, POSTS_PATH_ROOT},
-    render_markdown,
+    path::{AttachmentsPath, PostsPath, ATTACHMENTS_PATH_ROOT, POSTS_PATH_ROOT},
+    reader, render_markdown,
     rocket_eyre::{self, EyreReport},
+    watch::watch_and_render,
+    webmention::{self, send_removal_webmentions_after_render, spawn_send_webmentions},
     Command, PostMeta, TemplatedPost, Thread, SETTINGS,
 };
 
 use askama_rocket::Template;
 use chrono::{SecondsFormat, Utc};
 use clap::Parser as _;
-use jane_eyre::eyre::{Context, OptionExt as _};
+use jane_eyre::eyre::{self, bail, eyre, Context, OptionExt as _};
 use rocket::{
+    catch, catchers,
+    data::{Limits, ToByteUnit as _},
     form::Form,
-    fs::{FileServer, Options},
-    get, post,
-    response::{content, Redirect},
-    routes, Config, FromForm, Responder,
+    fs::{FileServer, Options, TempFile},
+    get,
+    http::{Cookie, CookieJar, Method, SameSite},
+    post,
+    response::{
+        content,
+        stream::{Event, EventStream},
+        Redirect,
+    },
+    routes,
+    tokio::{
+        select,
+        sync::broadcast::{self, error::RecvError, Sender},
+        time::sleep,
+    },
+    Config, FromForm, Request, Responder, Shutdown, State,
 };
+use tracing::{error, info, warn};
+use uuid::Uuid;
 
 #[derive(clap::Args, Debug)]
 pub struct Server {
     #[arg(short, long)]
     port: Option<u16>,
+
+    #[arg(
+        long,
+        help = "don’t render or reload pages when posts, attachments, or settings change"
+    )]
+    no_watch: bool,
 }
+
+/// sent to [`events_route`] subscribers whenever the site has been rendered again.
+#[derive(Clone, Copy, Debug)]
+struct Rendered;
 #[derive(askama_rocket::Template)]
 #[template(path = "compose.html")]
 struct ComposeTemplate {
     source: String,
+    /// `references_url` of the post being edited, or None if composing a new post.
+    edit_post: Option<String>,
+    csrf_token: Option<String>,
 }
 #[get("/compose?<reply_to>&<tags>&<is_transparent_share>")]
 fn compose_route(
+    user: User,
     reply_to: Option<String>,
     tags: Vec<String>,
     is_transparent_share: Option<bool>,
@@ -59,9 +96,11 @@ fn compose_route(
         references,
         title: (!is_transparent_share).then_some("headline".to_owned()),
         published: Some(now),
+        updated: None,
         author: SETTINGS.self_author.clone(),
         tags,
         is_transparent_share,
+        is_unpublished: false,
     };
     let meta = meta.render().wrap_err("failed to render template")?;
 
@@ -71,16 +110,43 @@ fn compose_route(
         format!("{meta}\n\npost body (accepts markdown!)")
     };
 
-    Ok(ComposeTemplate { source })
+    Ok(ComposeTemplate {
+        source,
+        edit_post: None,
+        csrf_token: user.csrf_token(),
+    })
+}
+
+#[get("/edit?<post>")]
+fn edit_route(user: User, post: String) -> rocket_eyre::Result<ComposeTemplate> {
+    let path = editable_post_path(&post)?;
+    let source = read_to_string(&path).wrap_err("failed to read post file")?;
+
+    Ok(ComposeTemplate {
+        source,
+        edit_post: Some(path.references_url()),
+        csrf_token: user.csrf_token(),
+    })
 }
 
 #[derive(FromForm, Debug)]
 struct Body<'r> {
     source: &'r str,
+    csrf: Option<&'r str>,
+}
+
+/// for routes that only need the `csrf` field, ignoring any other fields.
+#[derive(FromForm, Debug)]
+struct CsrfBody<'r> {
+    csrf: Option<&'r str>,
 }
 
 #[post("/preview", data = "<body>")]
-fn preview_route(body: Form<Body<'_>>) -> rocket_eyre::Result<content::RawHtml<String>> {
+fn preview_route(
+    user: User,
+    body: Form<Body<'_>>,
+) -> rocket_eyre::Result<content::RawHtml<String>> {
+    user.check_csrf(body.csrf)?;
     let unsafe_source = body.source;
     let unsafe_html = render_markdown(unsafe_source);
     let post = TemplatedPost::filter(&unsafe_html, None)?;
@@ -90,6 +156,67 @@ fn preview_route(body: Form<Body<'_>>) -> rocket_eyre::Result<content::RawHtml<S
     ))
 }
 
+#[derive(FromForm, Debug)]
+struct Upload<'r> {
+    file: TempFile<'r>,
+    csrf: Option<String>,
+}
+
+/// stores an uploaded file as an attachment, returning its url relative to `base_url`.
+#[post("/attach", data = "<upload>")]
+async fn attach_route(user: User, mut upload: Form<Upload<'_>>) -> rocket_eyre::Result<String> {
+    user.check_csrf(upload.csrf.as_deref())?;
+    let attachment_path = store_upload(&mut upload.file).await?;
+
+    Ok(attachment_path.site_path()?.base_relative_url())
+}
+
+/// stores an uploaded file as an attachment, via [`RealAttachmentsContext::store`].
+pub async fn store_upload(file: &mut TempFile<'_>) -> eyre::Result<AttachmentsPath> {
+    let filename = file
+        .raw_name()
+        .map(|name| upload_filename(name.dangerous_unsafe_unsanitized_raw().as_str()))
+        .unwrap_or_else(|| "file".to_owned());
+
+    // `store` takes the path of an existing file, and the filename becomes part of the url, so
+    // copy the upload to a temporary directory under its original filename first.
+    let temp_dir = env::temp_dir().join(format!("autost-upload-{}", Uuid::new_v4()));
+    create_dir_all(&temp_dir)?;
+    let temp_path = temp_dir.join(&filename);
+    file.copy_to(&temp_path).await?;
+    create_dir_all(&*ATTACHMENTS_PATH_ROOT)?;
+    let result = RealAttachmentsContext.store(&temp_path);
+    remove_dir_all(&temp_dir)?;
+    let attachment_path = result?;
+    info!("created attachment: {attachment_path:?}");
+
+    Ok(attachment_path)
+}
+
+/// makes an uploaded filename safe to use as the last component of an attachment path.
+fn upload_filename(raw_name: &str) -> String {
+    let name = raw_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    match name.trim() {
+        "" | "." | ".." => "file".to_owned(),
+        _ => name,
+    }
+}
+
+#[test]
+fn test_upload_filename() {
+    assert_eq!(upload_filename("photo.jpg"), "photo.jpg");
+    assert_eq!(upload_filename("C:\\Users\\me\\photo.jpg"), "photo.jpg");
+    assert_eq!(upload_filename("../../etc/passwd"), "passwd");
+    assert_eq!(upload_filename(".."), "file");
+    assert_eq!(upload_filename("a\nb.png"), "ab.png");
+}
+
 #[derive(Responder)]
 enum PublishResponse {
     Redirect(Box<Redirect>),
@@ -97,7 +224,12 @@ enum PublishResponse {
 }
 
 #[post("/publish?<js>", data = "<body>")]
-fn publish_route(js: Option<bool>, body: Form<Body<'_>>) -> rocket_eyre::Result<PublishResponse> {
+fn publish_route(
+    user: User,
+    js: Option<bool>,
+    body: Form<Body<'_>>,
+) -> rocket_eyre::Result<PublishResponse> {
+    user.check_csrf(body.csrf)?;
     let js = js.unwrap_or_default();
     let unsafe_source = body.source;
 
@@ -106,19 +238,9 @@ fn publish_route(js: Option<bool>, body: Form<Body<'_>>) -> rocket_eyre::Result<
     let post = TemplatedPost::filter(&unsafe_html, None)?;
     let _thread = Thread::try_from(post)?;
 
-    // cohost post ids are all less than 10000000.
-    let (mut file, path) = (10000000..)
-        .map(|id| {
-            let path = PostsPath::markdown_post_path(id);
-            File::create_new(&path).map(|file| (file, path))
-        })
-        .find(|file| !matches!(file, Err(error) if error.kind() == io::ErrorKind::AlreadyExists))
-        .expect("too many posts :(")
-        .wrap_err("failed to create post")?;
-
-    file.write_all(unsafe_source.as_bytes())
-        .wrap_err("failed to write post file")?;
+    let path = PostsPath::create_markdown_post(unsafe_source.as_bytes())?;
     render_all()?;
+    spawn_send_webmentions(vec![path.clone()]);
 
     let post = TemplatedPost::load(&path)?;
     let _thread = Thread::try_from(post)?;
@@ -136,6 +258,250 @@ fn publish_route(js: Option<bool>, body: Form<Body<'_>>) -> rocket_eyre::Result<
     }
 }
 
+/// server-sent events for open pages, so they can reload when the site has been rendered again.
+#[get("/events")]
+fn events_route(sender: &State<Sender<Rendered>>, mut shutdown: Shutdown) -> EventStream![] {
+    let mut receiver = sender.subscribe();
+    EventStream! {
+        loop {
+            select! {
+                result = receiver.recv() => match result {
+                    Ok(Rendered) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => break,
+                },
+                _ = &mut shutdown => break,
+            }
+            yield Event::data("reload").event("reload");
+        }
+    }
+}
+
+#[post("/save?<post>&<js>", data = "<body>")]
+fn save_route(
+    user: User,
+    post: String,
+    js: Option<bool>,
+    body: Form<Body<'_>>,
+) -> rocket_eyre::Result<PublishResponse> {
+    user.check_csrf(body.csrf)?;
+    let js = js.unwrap_or_default();
+    let path = editable_post_path(&post)?;
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let unsafe_source = with_updated_meta(body.source, &now);
+
+    // try rendering the post before writing it, to catch any errors.
+    let unsafe_html = if path.is_markdown_post() {
+        render_markdown(&unsafe_source)
+    } else {
+        unsafe_source.clone()
+    };
+    let post = TemplatedPost::filter(&unsafe_html, None)?;
+    let _thread = Thread::try_from(post)?;
+
+    path.replace_contents(unsafe_source.as_bytes())?;
+    render_all()?;
+    spawn_send_webmentions(vec![path.clone()]);
+
+    let url = path
+        .rendered_path()?
+        .ok_or_eyre("path has no rendered path")?
+        .internal_url();
+    if js {
+        Ok(PublishResponse::Text(url))
+    } else {
+        Ok(PublishResponse::Redirect(Box::new(Redirect::to(url))))
+    }
+}
+
+#[post("/unpublish?<post>&<js>", data = "<body>")]
+fn unpublish_route(
+    user: User,
+    post: String,
+    js: Option<bool>,
+    body: Form<CsrfBody<'_>>,
+) -> rocket_eyre::Result<PublishResponse> {
+    user.check_csrf(body.csrf)?;
+    let path = editable_post_path(&post)?;
+    let removed_post = unpublish(&path)?;
+    render_all()?;
+    send_removal_webmentions_after_render(vec![removed_post])?;
+
+    Ok(redirect_to_index(js.unwrap_or_default()))
+}
+
+#[post("/delete?<post>&<js>", data = "<body>")]
+fn delete_route(
+    user: User,
+    post: String,
+    js: Option<bool>,
+    body: Form<CsrfBody<'_>>,
+) -> rocket_eyre::Result<PublishResponse> {
+    user.check_csrf(body.csrf)?;
+    let path = editable_post_path(&post)?;
+    let removed_post = delete(&path)?;
+    render_all()?;
+    send_removal_webmentions_after_render(vec![removed_post])?;
+
+    Ok(redirect_to_index(js.unwrap_or_default()))
+}
+
+fn redirect_to_index(js: bool) -> PublishResponse {
+    if js {
+        PublishResponse::Text(SETTINGS.base_url.clone())
+    } else {
+        PublishResponse::Redirect(Box::new(Redirect::to(&SETTINGS.base_url)))
+    }
+}
+
+/// parses a `?post=` parameter into the path of an existing post.
+fn editable_post_path(post: &str) -> rocket_eyre::Result<PostsPath> {
+    let path = POSTS_PATH_ROOT.join(post).map_err(EyreReport::BadRequest)?;
+    if path.rendered_path()?.is_none() {
+        return Err(EyreReport::BadRequest(eyre!("not a post: {post}")));
+    }
+    if !path.as_ref().is_file() {
+        return Err(EyreReport::BadRequest(eyre!("no such post: {post}")));
+    }
+
+    Ok(path)
+}
+
+/// replaces any `<meta name="updated">` lines in the post source with one for the given time,
+/// placed after the `<meta name="published">` line if any, or at the start of the post otherwise.
+fn with_updated_meta(source: &str, updated: &str) -> String {
+    let updated_meta = format!(r#"<meta name="updated" content="{updated}">"#);
+    let mut lines = source
+        .split_inclusive('\n')
+        .filter(|line| !line.trim_start().starts_with(r#"<meta name="updated""#))
+        .collect::<Vec<_>>();
+    let index = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with(r#"<meta name="published""#))
+        .map_or(0, |i| i + 1);
+    let updated_line = format!("{updated_meta}\n");
+    lines.insert(index, &updated_line);
+
+    lines.concat()
+}
+
+#[test]
+fn test_with_updated_meta() {
+    let now = "2024-12-25T00:00:00.000Z";
+    assert_eq!(
+        with_updated_meta(
+            "<meta name=\"published\" content=\"2024\">\n<meta name=\"updated\" content=\"2024-06\">\n\nbody\n",
+            now
+        ),
+        format!("<meta name=\"published\" content=\"2024\">\n<meta name=\"updated\" content=\"{now}\">\n\nbody\n")
+    );
+    assert_eq!(
+        with_updated_meta("body", now),
+        format!("<meta name=\"updated\" content=\"{now}\">\nbody")
+    );
+}
+
+#[derive(askama_rocket::Template)]
+#[template(path = "login.html")]
+struct LoginTemplate {
+    next: String,
+    failed: bool,
+}
+
+#[get("/login?<next>")]
+fn login_route(next: Option<String>) -> LoginTemplate {
+    LoginTemplate {
+        next: next.unwrap_or_default(),
+        failed: false,
+    }
+}
+
+#[derive(FromForm, Debug)]
+struct Login<'r> {
+    password: &'r str,
+    next: Option<&'r str>,
+}
+
+#[derive(Responder)]
+enum LoginResponse {
+    Redirect(Box<Redirect>),
+    #[response(status = 401)]
+    Failed(LoginTemplate),
+}
+
+#[post("/login", data = "<body>")]
+async fn login_submit_route(
+    sessions: &State<Sessions>,
+    cookies: &CookieJar<'_>,
+    body: Form<Login<'_>>,
+) -> rocket_eyre::Result<LoginResponse> {
+    // only redirect to our own pages, not to other sites.
+    let next = body
+        .next
+        .filter(|next| {
+            next.starts_with(&SETTINGS.base_url)
+                && !next.starts_with("//")
+                && !next.starts_with("/\\")
+        })
+        .map_or_else(
+            || format!("{}compose", SETTINGS.base_url),
+            |next| next.to_owned(),
+        );
+
+    let Some(session_id) = sessions.login(body.password.to_owned()).await? else {
+        warn!("failed login attempt");
+        // slow down anyone guessing passwords.
+        sleep(Duration::from_secs(1)).await;
+        return Ok(LoginResponse::Failed(LoginTemplate { next, failed: true }));
+    };
+    cookies.add(
+        Cookie::build((SESSION_COOKIE, session_id))
+            .path(SETTINGS.base_url.clone())
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .max_age(SESSION_LIFETIME.try_into()?)
+            // only on loopback can the server be reached without https.
+            .secure(!SETTINGS.server_address().is_loopback()),
+    );
+
+    Ok(LoginResponse::Redirect(Box::new(Redirect::to(next))))
+}
+
+#[post("/logout", data = "<body>")]
+fn logout_route(
+    user: User,
+    sessions: &State<Sessions>,
+    cookies: &CookieJar<'_>,
+    body: Form<CsrfBody<'_>>,
+) -> rocket_eyre::Result<Redirect> {
+    user.check_csrf(body.csrf)?;
+    if let Some(cookie) = cookies.get(SESSION_COOKIE) {
+        sessions.logout(cookie.value());
+    }
+    cookies.remove(Cookie::build(SESSION_COOKIE).path(SETTINGS.base_url.clone()));
+
+    Ok(Redirect::to(&SETTINGS.base_url))
+}
+
+#[derive(Responder)]
+enum UnauthorizedResponse {
+    Redirect(Box<Redirect>),
+    Text(&'static str),
+}
+
+/// send people who are not logged in to the login page, unless the request came from a script.
+#[catch(401)]
+fn unauthorized_catcher(request: &Request<'_>) -> UnauthorizedResponse {
+    if request.method() == Method::Get {
+        let next = urlencoding::encode(&request.uri().to_string()).into_owned();
+        UnauthorizedResponse::Redirect(Box::new(Redirect::to(format!(
+            "{}login?next={next}",
+            SETTINGS.base_url
+        ))))
+    } else {
+        UnauthorizedResponse::Text("not logged in")
+    }
+}
+
 // lower than FileServer, which uses rank 10 by default
 #[get("/", rank = 100)]
 fn root_route() -> Redirect {
@@ -148,27 +514,93 @@ fn root_route() -> Redirect {
 ///     - `?tags=<str>` (optional; any number of times)
 ///     - `?is_transparent_share` (optional)
 ///   - `POST <base_url>preview` (`preview_route`)
+///   - `POST <base_url>attach` (`attach_route`)
+///     - multipart form with a `file`
 ///   - `POST <base_url>publish` (`publish_route`)
+///   - `GET <base_url>edit?post=<PostsPath>` (`edit_route`)
+///   - `POST <base_url>save?post=<PostsPath>` (`save_route`)
+///   - `POST <base_url>unpublish?post=<PostsPath>` (`unpublish_route`)
+///   - `POST <base_url>delete?post=<PostsPath>` (`delete_route`)
+///   - `GET <base_url>events` (`events_route`)
+///   - `GET <base_url>login?next=<url>` (`login_route`)
+///   - `POST <base_url>login` (`login_submit_route`)
+///   - `POST <base_url>logout` (`logout_route`)
+///   - `GET <base_url>micropub` and `POST <base_url>micropub` (see [`micropub`])
+///   - `POST <base_url>micropub/media` (see [`micropub`])
+///   - `POST <base_url>webmention` (see [`webmention`])
+///   - `GET <base_url>reader?page=<usize>` (see [`reader`])
 ///   - `GET <base_url><path>` (`static_route`)
 /// - `GET /` (`root_route`)
+///
+/// if `server_password_hash` is set, all of the site routes except `events`, `login`, `micropub`,
+/// `webmention`, and the static files need you to log in, and all of the `POST` routes except
+/// those need a `csrf` form field. `micropub` needs a bearer token instead (see
+/// `micropub_token_hash`), and `webmention` is open to everyone.
 #[rocket::main]
 pub async fn main() -> jane_eyre::eyre::Result<()> {
     let Command::Server(args) = Command::parse() else {
         unreachable!("guaranteed by subcommand call in entry point")
     };
 
+    let address = SETTINGS.server_address();
+    if !address.is_loopback() {
+        if SETTINGS.server_password_hash.is_none() {
+            bail!("server_address is not a loopback address, so server_password_hash setting must be set!");
+        }
+        warn!("listening on {address}, which is not a loopback address; make sure you serve this over https, such as with a reverse proxy");
+    }
+
     render_all()?;
 
+    let (sender, _) = broadcast::channel(16);
+    if !args.no_watch {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            if let Err(error) = watch_and_render(|_| {
+                // no subscribers is fine, it just means there are no open pages.
+                let _ = sender.send(Rendered);
+            }) {
+                error!(?error, "failed to watch for changes");
+            }
+        });
+    }
+
     let port = args.port.unwrap_or(SETTINGS.server_port());
     let _rocket = rocket::custom(
         Config::figment()
             .merge(("port", port))
-            .merge(("address", "::1")),
+            .merge(("address", address))
+            // allow attachments of any reasonable size.
+            .merge((
+                "limits",
+                Limits::default()
+                    .limit("data-form", 1.gibibytes())
+                    .limit("file", 1.gibibytes()),
+            )),
     )
+    .manage(sender)
+    .manage(Sessions::default())
+    .register(&SETTINGS.base_url, catchers![unauthorized_catcher])
     .mount(
         &SETTINGS.base_url,
-        routes![compose_route, preview_route, publish_route],
+        routes![
+            compose_route,
+            edit_route,
+            preview_route,
+            attach_route,
+            publish_route,
+            save_route,
+            unpublish_route,
+            delete_route,
+            events_route,
+            login_route,
+            login_submit_route,
+            logout_route
+        ],
     )
+    .mount(&SETTINGS.base_url, micropub::routes())
+    .mount(&SETTINGS.base_url, webmention::routes())
+    .mount(&SETTINGS.base_url, reader::routes())
     .mount("/", routes![root_route])
     // serve attachments out of main attachment store, in case we need to preview a post
     // that refers to an attachment for the first time. otherwise they will 404, since
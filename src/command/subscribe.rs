@@ -0,0 +1,56 @@
+use clap::Parser as _;
+use jane_eyre::eyre;
+use reqwest::Client;
+use tracing::info;
+use url::Url;
+
+use crate::{
+    reader::{find_feed, Subscription, Subscriptions},
+    Command,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Subscribe {
+    /// atom feed, rss feed, or html page with h-entries or a link to a feed.
+    url: String,
+
+    #[arg(long, help = "list the subscription in the blogroll")]
+    public: bool,
+}
+
+#[tokio::main]
+pub async fn main() -> eyre::Result<()> {
+    let Command::Subscribe(args) = Command::parse() else {
+        unreachable!("guaranteed by subcommand call in entry point")
+    };
+
+    let url = Url::parse(&args.url)?;
+    let (url, feed) = find_feed(&Client::new(), &url).await?;
+    let mut subscriptions = Subscriptions::load()?;
+    if let Some(subscription) = subscriptions
+        .subscriptions
+        .iter_mut()
+        .find(|subscription| subscription.url == url.as_str())
+    {
+        if args.public && !subscription.public {
+            subscription.public = true;
+            subscriptions.save()?;
+            info!("already subscribed to {url}, now listed in the blogroll");
+        } else {
+            info!("already subscribed to {url}");
+        }
+        return Ok(());
+    }
+
+    let mut subscription = Subscription::new(&url, &feed);
+    subscription.public = args.public;
+    info!(
+        "subscribed to {} ({} entries); run `autost fetch` to fetch them",
+        subscription.name(),
+        feed.entries.len()
+    );
+    subscriptions.subscriptions.push(subscription);
+    subscriptions.save()?;
+
+    Ok(())
+}
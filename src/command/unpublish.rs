@@ -0,0 +1,69 @@
+use std::fs::{read_to_string, remove_file};
+
+use jane_eyre::eyre::{self, bail, OptionExt as _};
+use tracing::info;
+
+use crate::{
+    command::render::render_all,
+    path::PostsPath,
+    webmention::{send_removal_webmentions_after_render, take_sent_webmentions, RemovedPost},
+    TemplatedPost,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Unpublish {
+    #[arg(required = true)]
+    post_paths: Vec<String>,
+}
+
+pub fn main(args: Unpublish) -> eyre::Result<()> {
+    let mut removed_posts = vec![];
+    for path in args.post_paths {
+        let path = PostsPath::from_site_root_relative_path(&path)?;
+        removed_posts.push(unpublish(&path)?);
+    }
+    render_all()?;
+    send_removal_webmentions_after_render(removed_posts)?;
+
+    Ok(())
+}
+
+/// marks the post with `<meta name="is_unpublished">`, so that render skips it, and removes its
+/// rendered page and its sent webmentions. the caller is responsible for rendering the site again,
+/// then sending webmentions for the returned post (see [`send_removal_webmentions_after_render`]).
+pub fn unpublish(path: &PostsPath) -> eyre::Result<RemovedPost> {
+    check_post_exists(path)?;
+    if TemplatedPost::load(path)?.meta.is_unpublished {
+        info!(?path, "post is already unpublished");
+    } else {
+        info!(?path, "unpublishing post");
+        let source = read_to_string(path)?;
+        path.replace_contents(format!("<meta name=\"is_unpublished\">\n{source}").as_bytes())?;
+    }
+    remove_rendered_page(path)?;
+
+    take_sent_webmentions(path)
+}
+
+pub fn check_post_exists(path: &PostsPath) -> eyre::Result<()> {
+    if path.rendered_path()?.is_none() {
+        bail!("not a post: {path:?}");
+    }
+    if !path.as_ref().is_file() {
+        bail!("no such post: {path:?}");
+    }
+
+    Ok(())
+}
+
+pub fn remove_rendered_page(path: &PostsPath) -> eyre::Result<()> {
+    let rendered_path = path
+        .rendered_path()?
+        .ok_or_eyre("path has no rendered path")?;
+    if rendered_path.as_ref().exists() {
+        info!("removing {rendered_path:?}");
+        remove_file(&rendered_path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use jane_eyre::eyre::{self, bail};
+use tracing::info;
+
+use crate::reader::Subscriptions;
+
+#[derive(clap::Args, Debug)]
+pub struct Unsubscribe {
+    url: String,
+}
+
+/// removes the subscription, but keeps the posts that were fetched for it.
+pub fn main(args: Unsubscribe) -> eyre::Result<()> {
+    let mut subscriptions = Subscriptions::load()?;
+    let old_len = subscriptions.subscriptions.len();
+    subscriptions
+        .subscriptions
+        .retain(|subscription| subscription.url != args.url);
+    if subscriptions.subscriptions.len() == old_len {
+        bail!("not subscribed to {}", args.url);
+    }
+    subscriptions.save()?;
+    info!("unsubscribed from {}", args.url);
+
+    Ok(())
+}
@@ -0,0 +1,668 @@
+//! parsing other sites’ feeds into posts: atom feeds, rss feeds, and html pages with h-entries.
+
+use chrono::{DateTime, SecondsFormat};
+use jane_eyre::eyre::{self, bail, OptionExt};
+use markup5ever_rcdom::{Handle, NodeData};
+use tracing::warn;
+use url::Url;
+
+use crate::{
+    command::import::{
+        document_base_href, h_entry_post, is_nested_in_other, mf2_find, mf2_find_all, mf2_p,
+        FetchPostResult,
+    },
+    dom::{
+        parse_html_document, parse_html_fragment, parse_xml, serialize_node_contents, text_content,
+        AttrsRefExt, BreadthTraverse, QualName, QualNameExt, TendrilExt,
+    },
+    Author, PostMeta,
+};
+
+const ATOM: &str = "http://www.w3.org/2005/Atom";
+const XHTML: &str = "http://www.w3.org/1999/xhtml";
+const XML: &str = "http://www.w3.org/XML/1998/namespace";
+const RSS_CONTENT: &str = "http://purl.org/rss/1.0/modules/content/";
+const DUBLIN_CORE: &str = "http://purl.org/dc/elements/1.1/";
+
+#[derive(Debug)]
+pub struct Feed {
+    pub title: Option<String>,
+    /// the page the feed is for, or the url of the feed if it doesn’t say.
+    pub home_url: Url,
+    /// in the order they appear in the feed.
+    pub entries: Vec<FetchPostResult>,
+}
+
+/// a feed listed in an opml file.
+#[derive(Debug)]
+pub struct OpmlFeed {
+    pub xml_url: Url,
+    pub title: Option<String>,
+    pub html_url: Option<Url>,
+}
+
+/// parses an atom feed, rss feed, or html page with h-entries, fetched from `url`. returns None
+/// if the document is none of those.
+///
+/// entries with no url are skipped, since the url is what tells us if we have seen them before.
+pub fn parse_feed(body: &[u8], url: &Url) -> eyre::Result<Option<Feed>> {
+    let dom = parse_xml(body)?;
+    if let Some(root) = document_element(&dom.document) {
+        if is_named(&root, ATOM, "feed") {
+            return Ok(Some(parse_atom_feed(&root, url)?));
+        }
+        if is_named(&root, "", "rss") {
+            return Ok(Some(parse_rss_feed(&root, url)?));
+        }
+    }
+
+    let dom = parse_html_document(body)?;
+    parse_h_feed(dom.document.clone(), url)
+}
+
+/// finds the url of an atom or rss feed linked from an html page, like
+/// `<link rel=alternate type=application/atom+xml href>`.
+pub fn discover_feed_url(body: &[u8], url: &Url) -> eyre::Result<Option<Url>> {
+    let dom = parse_html_document(body)?;
+    let base_href = document_base_href(dom.document.clone(), url)?;
+    for node in BreadthTraverse::elements(dom.document.clone()) {
+        let NodeData::Element { name, attrs, .. } = &node.data else {
+            unreachable!()
+        };
+        if name != &QualName::html("link") {
+            continue;
+        }
+        let attrs = attrs.borrow();
+        let is_alternate = attrs
+            .attr_str("rel")?
+            .is_some_and(|rel| rel.split_ascii_whitespace().any(|rel| rel == "alternate"));
+        let is_feed = attrs.attr_str("type")?.is_some_and(|r#type| {
+            r#type == "application/atom+xml" || r#type == "application/rss+xml"
+        });
+        if let Some(href) = attrs.attr_str("href")?.filter(|_| is_alternate && is_feed) {
+            return Ok(Some(base_href.join(href)?));
+        }
+    }
+
+    Ok(None)
+}
+
+/// parses the feeds listed in an opml file, that is, every `<outline>` with an `xmlUrl`, however
+/// they are nested. relative urls are resolved against `url`.
+///
+/// <https://opml.org/spec2.opml#subscriptionLists>
+pub fn parse_opml(body: &[u8], url: &Url) -> eyre::Result<Vec<OpmlFeed>> {
+    let dom = parse_xml(body)?;
+    if !document_element(&dom.document).is_some_and(|root| is_named(&root, "", "opml")) {
+        bail!("not an opml file");
+    }
+
+    let mut result = vec![];
+    for outline in BreadthTraverse::elements(dom.document.clone()) {
+        if !is_named(&outline, "", "outline") {
+            continue;
+        }
+        let Some(xml_url) = attr(&outline, "xmlUrl")? else {
+            continue;
+        };
+        let Ok(xml_url) = url.join(xml_url.trim()) else {
+            warn!(xml_url, "skipping opml outline with bad xmlUrl");
+            continue;
+        };
+        let title = attr(&outline, "title")?
+            .or(attr(&outline, "text")?)
+            .map(|title| title.trim().to_owned())
+            .filter(|title| !title.is_empty());
+        let html_url = attr(&outline, "htmlUrl")?.and_then(|html_url| url.join(&html_url).ok());
+        result.push(OpmlFeed {
+            xml_url,
+            title,
+            html_url,
+        });
+    }
+
+    Ok(result)
+}
+
+/// <https://www.rfc-editor.org/rfc/rfc4287>
+fn parse_atom_feed(feed: &Handle, url: &Url) -> eyre::Result<Feed> {
+    let base_href = xml_base(feed, url)?;
+    let title = child(feed, ATOM, "title").map(atom_text).transpose()?;
+    let home_url = atom_alternate_link(feed, &base_href)?.unwrap_or(url.clone());
+    let feed_author = match child(feed, ATOM, "author") {
+        Some(author) => Some(atom_person(&author, &base_href, &home_url)?),
+        None => title.as_deref().map(|title| site_author(title, &home_url)),
+    };
+
+    let mut entries = vec![];
+    for entry in children(feed, ATOM, "entry") {
+        let base_href = xml_base(&entry, &base_href)?;
+        let id = child(&entry, ATOM, "id")
+            .map(text_content)
+            .transpose()?
+            .and_then(|id| Url::parse(id.trim()).ok())
+            .filter(|id| matches!(id.scheme(), "http" | "https"));
+        let Some(entry_url) = atom_alternate_link(&entry, &base_href)?.or(id) else {
+            warn!("skipping atom entry with no link");
+            continue;
+        };
+        let author = match child(&entry, ATOM, "author") {
+            Some(author) => Some(atom_person(&author, &base_href, &home_url)?),
+            None => feed_author.clone(),
+        };
+        let content = match child(&entry, ATOM, "content").or(child(&entry, ATOM, "summary")) {
+            Some(content) => atom_content(&content, &base_href)?,
+            None => String::new(),
+        };
+        let published = child_text(&entry, ATOM, "published")?;
+        let updated = child_text(&entry, ATOM, "updated")?;
+        let tags = children(&entry, ATOM, "category")
+            .into_iter()
+            .map(|category| attr(&category, "term"))
+            .collect::<eyre::Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        entries.push(FetchPostResult {
+            base_href,
+            content,
+            url: entry_url.clone(),
+            meta: PostMeta {
+                archived: Some(entry_url.to_string()),
+                title: child(&entry, ATOM, "title").map(atom_text).transpose()?,
+                // atom entries need not have a published date, but they always have an updated date.
+                published: published.clone().or(updated.clone()),
+                updated: updated.filter(|updated| published.is_some_and(|p| p != *updated)),
+                author,
+                tags,
+                ..Default::default()
+            },
+            parent_url: None,
+            ancestors: vec![],
+        });
+    }
+
+    Ok(Feed {
+        title,
+        home_url,
+        entries,
+    })
+}
+
+/// <https://www.rssboard.org/rss-specification>
+fn parse_rss_feed(rss: &Handle, url: &Url) -> eyre::Result<Feed> {
+    let channel = child(rss, "", "channel").ok_or_eyre("<rss> has no <channel>")?;
+    let title = child_text(&channel, "", "title")?;
+    let home_url = child_text(&channel, "", "link")?
+        .and_then(|link| url.join(&link).ok())
+        .unwrap_or(url.clone());
+    let feed_author = title.as_deref().map(|title| site_author(title, &home_url));
+
+    let mut entries = vec![];
+    for item in children(&channel, "", "item") {
+        let link = child_text(&item, "", "link")?;
+        let guid = child(&item, "", "guid")
+            .filter(|guid| attr(guid, "isPermaLink").ok().flatten().as_deref() != Some("false"))
+            .map(text_content)
+            .transpose()?;
+        let Some(item_url) = link.or(guid).and_then(|link| url.join(link.trim()).ok()) else {
+            warn!("skipping rss item with no link");
+            continue;
+        };
+        // <dc:creator> is a name, but <author> is an email address, optionally followed by a name
+        // in parentheses.
+        let author =
+            match child_text(&item, DUBLIN_CORE, "creator")?.or(child_text(&item, "", "author")?) {
+                Some(author) => {
+                    let name = author
+                        .split_once(" (")
+                        .and_then(|(_, name)| name.strip_suffix(")"))
+                        .unwrap_or(&author);
+                    Some(site_author(name, &home_url))
+                }
+                None => feed_author.clone(),
+            };
+        // <description> is usually html, but <content:encoded> is always html.
+        let content = child_text(&item, RSS_CONTENT, "encoded")?
+            .or(child_text(&item, "", "description")?)
+            .unwrap_or_default();
+        let published = child_text(&item, "", "pubDate")?.map(|date| {
+            DateTime::parse_from_rfc2822(&date)
+                .map(|date| date.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+                .unwrap_or(date)
+        });
+        let tags = children(&item, "", "category")
+            .into_iter()
+            .map(text_content)
+            .collect::<eyre::Result<Vec<_>>>()?
+            .into_iter()
+            .map(|tag| tag.trim().to_owned())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+
+        entries.push(FetchPostResult {
+            base_href: item_url.clone(),
+            content,
+            url: item_url.clone(),
+            meta: PostMeta {
+                archived: Some(item_url.to_string()),
+                title: child_text(&item, "", "title")?,
+                published,
+                author,
+                tags,
+                ..Default::default()
+            },
+            parent_url: None,
+            ancestors: vec![],
+        });
+    }
+
+    Ok(Feed {
+        title,
+        home_url,
+        entries,
+    })
+}
+
+/// <https://microformats.org/wiki/h-feed>
+///
+/// if the page has no `.h-feed`, the top-level h-entries on the page are the feed.
+fn parse_h_feed(document: Handle, url: &Url) -> eyre::Result<Option<Feed>> {
+    let base_href = document_base_href(document.clone(), url)?;
+    let h_feed = mf2_find(document.clone(), "h-feed").unwrap_or(document.clone());
+    let mut h_entries = vec![];
+    for h_entry in mf2_find_all(h_feed.clone(), "h-entry") {
+        if !is_nested_in_other(h_entry.clone(), "h-entry", &h_entry)? {
+            h_entries.push(h_entry);
+        }
+    }
+    if h_entries.is_empty() {
+        return Ok(None);
+    }
+
+    // the name of the h-feed, not of one of its entries, or else the title of the page.
+    let mut title = None;
+    for p_name in mf2_find_all(h_feed.clone(), "p-name") {
+        if !is_nested_in_other(p_name.clone(), "h-entry", &h_feed)? {
+            title = mf2_p(p_name, "p-name")?;
+            break;
+        }
+    }
+    if title.is_none() {
+        let title_element = BreadthTraverse::elements(document.clone())
+            .find(|node| matches!(&node.data, NodeData::Element { name, .. } if name == &QualName::html("title")));
+        title = title_element
+            .map(text_content)
+            .transpose()?
+            .map(|title| title.trim().to_owned())
+            .filter(|title| !title.is_empty());
+    }
+    let feed_author = site_author(title.as_deref().unwrap_or(url.authority()), url);
+
+    let mut entries = vec![];
+    for h_entry in h_entries {
+        match h_entry_post(h_entry, &base_href) {
+            Ok(mut entry) => {
+                entry.meta.author.get_or_insert(feed_author.clone());
+                entries.push(entry);
+            }
+            Err(error) => warn!(?error, "skipping h-entry"),
+        }
+    }
+
+    Ok(Some(Feed {
+        title,
+        home_url: url.clone(),
+        entries,
+    }))
+}
+
+/// an author for posts that only say which site they are from.
+fn site_author(name: &str, url: &Url) -> Author {
+    Author {
+        href: url.to_string(),
+        name: name.to_owned(),
+        display_name: name.to_owned(),
+        display_handle: url.authority().to_owned(),
+    }
+}
+
+/// `<author>` in atom, falling back to the given url if the author has no `<uri>`.
+fn atom_person(person: &Handle, base_href: &Url, fallback_url: &Url) -> eyre::Result<Author> {
+    let url = child_text(person, ATOM, "uri")?
+        .and_then(|uri| base_href.join(&uri).ok())
+        .unwrap_or(fallback_url.clone());
+    let name = child_text(person, ATOM, "name")?.unwrap_or(url.authority().to_owned());
+
+    Ok(site_author(&name, &url))
+}
+
+/// the first `<link>` with no `rel` or `rel="alternate"`, preferring any with `type="text/html"`.
+fn atom_alternate_link(node: &Handle, base_href: &Url) -> eyre::Result<Option<Url>> {
+    let mut result = None;
+    for link in children(node, ATOM, "link") {
+        if !matches!(attr(&link, "rel")?.as_deref(), None | Some("alternate")) {
+            continue;
+        }
+        let Some(href) = attr(&link, "href")? else {
+            continue;
+        };
+        let is_html = attr(&link, "type")?.is_some_and(|r#type| r#type == "text/html");
+        if is_html || result.is_none() {
+            result = Some(base_href.join(&href)?);
+        }
+        if is_html {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+/// atom text constructs as plain text, like `<title>`.
+fn atom_text(node: Handle) -> eyre::Result<String> {
+    let text = match attr(&node, "type")?.as_deref() {
+        Some("html") => {
+            let dom = parse_html_fragment(text_content(node)?.as_bytes())?;
+            text_content(dom.document.clone())?
+        }
+        _ => text_content(node)?,
+    };
+
+    Ok(text.trim().to_owned())
+}
+
+/// atom `<content>` or `<summary>` as html.
+fn atom_content(node: &Handle, base_href: &Url) -> eyre::Result<String> {
+    if let Some(src) = attr(node, "src")? {
+        let src = escape_html(base_href.join(&src)?.as_str());
+        return Ok(format!(r#"<p><a href="{src}">{src}</a></p>"#));
+    }
+
+    Ok(match attr(node, "type")?.as_deref() {
+        Some("html") => text_content(node.clone())?,
+        Some("xhtml") => match child(node, XHTML, "div") {
+            Some(div) => serialize_node_contents(div)?,
+            None => String::new(),
+        },
+        Some("text") | None => {
+            format!("<p>{}</p>", escape_html(text_content(node.clone())?.trim()))
+        }
+        Some(other) => {
+            warn!(r#type = other, "skipping atom content of unknown type");
+            String::new()
+        }
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn xml_base(node: &Handle, base_href: &Url) -> eyre::Result<Url> {
+    let NodeData::Element { attrs, .. } = &node.data else {
+        return Ok(base_href.clone());
+    };
+    let xml_base = attrs
+        .borrow()
+        .iter()
+        .find(|attr| &*attr.name.ns == XML && &*attr.name.local == "base")
+        .map(|attr| attr.value.to_str().to_owned());
+
+    Ok(match xml_base {
+        Some(xml_base) => base_href.join(&xml_base)?,
+        None => base_href.clone(),
+    })
+}
+
+fn document_element(document: &Handle) -> Option<Handle> {
+    document
+        .children
+        .borrow()
+        .iter()
+        .find(|node| matches!(node.data, NodeData::Element { .. }))
+        .cloned()
+}
+
+fn is_named(node: &Handle, ns: &str, local: &str) -> bool {
+    matches!(&node.data, NodeData::Element { name, .. } if &*name.ns == ns && &*name.local == local)
+}
+
+fn children(node: &Handle, ns: &str, local: &str) -> Vec<Handle> {
+    node.children
+        .borrow()
+        .iter()
+        .filter(|kid| is_named(kid, ns, local))
+        .cloned()
+        .collect()
+}
+
+fn child(node: &Handle, ns: &str, local: &str) -> Option<Handle> {
+    children(node, ns, local).into_iter().next()
+}
+
+/// the trimmed text of the first child element with the given name, if any and not empty.
+fn child_text(node: &Handle, ns: &str, local: &str) -> eyre::Result<Option<String>> {
+    let Some(child) = child(node, ns, local) else {
+        return Ok(None);
+    };
+    let text = text_content(child)?.trim().to_owned();
+
+    Ok((!text.is_empty()).then_some(text))
+}
+
+fn attr(node: &Handle, name: &str) -> eyre::Result<Option<String>> {
+    let NodeData::Element { attrs, .. } = &node.data else {
+        return Ok(None);
+    };
+    let result = attrs.borrow().attr_str(name)?.map(|value| value.to_owned());
+
+    Ok(result)
+}
+
+#[test]
+fn test_parse_atom_feed() -> eyre::Result<()> {
+    let url = Url::parse("https://example.net/feed.xml")?;
+    let feed = parse_feed(
+        br#"<?xml version="1.0" encoding="utf-8"?>
+        <feed xmlns="http://www.w3.org/2005/Atom">
+            <title>example</title>
+            <link href="https://example.net/"/>
+            <entry>
+                <id>tag:example.net,2024:1</id>
+                <title type="html">hello &amp;amp; goodbye</title>
+                <link rel="alternate" href="/posts/1.html"/>
+                <updated>2024-01-02T00:00:00Z</updated>
+                <published>2024-01-01T00:00:00Z</published>
+                <author><name>eggbug</name><uri>https://example.net/eggbug</uri></author>
+                <category term="cats"/>
+                <content type="html">&lt;p&gt;&lt;img src="cat.png"&gt;&lt;/p&gt;</content>
+            </entry>
+            <entry>
+                <id>https://example.net/posts/2.html</id>
+                <title>text</title>
+                <updated>2024-01-03T00:00:00Z</updated>
+                <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><p>a <b>b</b></p></div></content>
+            </entry>
+            <entry><id>tag:example.net,2024:3</id><title>no link</title></entry>
+        </feed>"#,
+        &url,
+    )?
+    .ok_or_eyre("not a feed")?;
+    assert_eq!(feed.title.as_deref(), Some("example"));
+    assert_eq!(feed.home_url.as_str(), "https://example.net/");
+    assert_eq!(feed.entries.len(), 2);
+
+    let entry = &feed.entries[0];
+    assert_eq!(entry.url.as_str(), "https://example.net/posts/1.html");
+    assert_eq!(entry.meta.title.as_deref(), Some("hello & goodbye"));
+    assert_eq!(
+        entry.meta.published.as_deref(),
+        Some("2024-01-01T00:00:00Z")
+    );
+    assert_eq!(entry.meta.updated.as_deref(), Some("2024-01-02T00:00:00Z"));
+    assert_eq!(entry.meta.tags, ["cats"]);
+    assert_eq!(entry.content, r#"<p><img src="cat.png"></p>"#);
+    let author = entry.meta.author.as_ref().ok_or_eyre("no author")?;
+    assert_eq!(author.href, "https://example.net/eggbug");
+    assert_eq!(author.name, "eggbug");
+
+    let entry = &feed.entries[1];
+    assert_eq!(entry.url.as_str(), "https://example.net/posts/2.html");
+    assert_eq!(
+        entry.meta.published.as_deref(),
+        Some("2024-01-03T00:00:00Z")
+    );
+    assert_eq!(entry.meta.updated, None);
+    assert_eq!(entry.content, "<p>a <b>b</b></p>");
+    let author = entry.meta.author.as_ref().ok_or_eyre("no author")?;
+    assert_eq!(author.href, "https://example.net/");
+    assert_eq!(author.name, "example");
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_rss_feed() -> eyre::Result<()> {
+    let url = Url::parse("https://example.net/rss.xml")?;
+    let feed = parse_feed(
+        br#"<?xml version="1.0"?>
+        <rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+            <channel>
+                <title>example</title>
+                <link>https://example.net/</link>
+                <item>
+                    <title>first</title>
+                    <link>https://example.net/1</link>
+                    <pubDate>Mon, 01 Jan 2024 00:00:00 +0000</pubDate>
+                    <dc:creator>eggbug</dc:creator>
+                    <category>cats</category>
+                    <description>summary</description>
+                    <content:encoded><![CDATA[<p>full <em>text</em></p>]]></content:encoded>
+                </item>
+                <item>
+                    <guid isPermaLink="true">https://example.net/2</guid>
+                    <author>eggbug@example.net (eggbug)</author>
+                    <description>&lt;p&gt;second&lt;/p&gt;</description>
+                </item>
+                <item><guid isPermaLink="false">2</guid><description>no link</description></item>
+            </channel>
+        </rss>"#,
+        &url,
+    )?
+    .ok_or_eyre("not a feed")?;
+    assert_eq!(feed.title.as_deref(), Some("example"));
+    assert_eq!(feed.home_url.as_str(), "https://example.net/");
+    assert_eq!(feed.entries.len(), 2);
+
+    let entry = &feed.entries[0];
+    assert_eq!(entry.url.as_str(), "https://example.net/1");
+    assert_eq!(entry.meta.title.as_deref(), Some("first"));
+    assert_eq!(
+        entry.meta.published.as_deref(),
+        Some("2024-01-01T00:00:00Z")
+    );
+    assert_eq!(entry.meta.tags, ["cats"]);
+    assert_eq!(entry.content, "<p>full <em>text</em></p>");
+    let author = entry.meta.author.as_ref().ok_or_eyre("no author")?;
+    assert_eq!(author.name, "eggbug");
+    assert_eq!(author.href, "https://example.net/");
+
+    let entry = &feed.entries[1];
+    assert_eq!(entry.url.as_str(), "https://example.net/2");
+    assert_eq!(entry.content, "<p>second</p>");
+    let author = entry.meta.author.as_ref().ok_or_eyre("no author")?;
+    assert_eq!(author.name, "eggbug");
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_h_feed() -> eyre::Result<()> {
+    let url = Url::parse("https://example.net/")?;
+    let feed = parse_feed(
+        br#"<!doctype html><title>example</title>
+        <div class="h-feed">
+            <article class="h-entry">
+                <a class="u-url" href="/1"><time class="dt-published" datetime="2024-01-01">1</time></a>
+                <div class="e-content">one <span class="h-entry"><span class="e-content">nested</span></span></div>
+            </article>
+            <article class="h-entry">
+                <a class="u-url" href="/2"></a>
+                <span class="p-author h-card">eggbug</span>
+                <div class="e-content">two</div>
+            </article>
+        </div>"#,
+        &url,
+    )?
+    .ok_or_eyre("not a feed")?;
+    assert_eq!(feed.title.as_deref(), Some("example"));
+    assert_eq!(feed.home_url.as_str(), "https://example.net/");
+    assert_eq!(feed.entries.len(), 2);
+    assert_eq!(feed.entries[0].url.as_str(), "https://example.net/1");
+    assert_eq!(
+        feed.entries[0].meta.author.as_ref().map(|a| &*a.name),
+        Some("example")
+    );
+    assert_eq!(feed.entries[1].url.as_str(), "https://example.net/2");
+    assert_eq!(
+        feed.entries[1].meta.author.as_ref().map(|a| &*a.name),
+        Some("eggbug")
+    );
+
+    assert!(parse_feed(b"<!doctype html><p>hello", &url)?.is_none());
+
+    Ok(())
+}
+
+#[test]
+fn test_discover_feed_url() -> eyre::Result<()> {
+    let url = Url::parse("https://example.net/blog/")?;
+    assert_eq!(
+        discover_feed_url(
+            br#"<!doctype html><link rel="stylesheet" href="style.css"><link rel="alternate" type="application/atom+xml" href="feed.xml">"#,
+            &url
+        )?
+        .map(String::from),
+        Some("https://example.net/blog/feed.xml".to_owned())
+    );
+    assert_eq!(discover_feed_url(b"<!doctype html>", &url)?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_opml() -> eyre::Result<()> {
+    let url = Url::parse("file:///home/eggbug/feeds.opml")?;
+    let feeds = parse_opml(
+        br#"<?xml version="1.0" encoding="utf-8"?>
+        <opml version="2.0">
+            <head><title>feeds</title></head>
+            <body>
+                <outline text="friends">
+                    <outline type="rss" text="example" xmlUrl="https://example.net/feed.xml" htmlUrl="https://example.net/"/>
+                </outline>
+                <outline type="rss" text="text" title="title" xmlUrl="https://example.org/rss.xml"/>
+                <outline text="no feed" htmlUrl="https://example.com/"/>
+            </body>
+        </opml>"#,
+        &url,
+    )?;
+    assert_eq!(feeds.len(), 2);
+    assert_eq!(feeds[0].xml_url.as_str(), "https://example.org/rss.xml");
+    assert_eq!(feeds[0].title.as_deref(), Some("title"));
+    assert_eq!(feeds[0].html_url, None);
+    assert_eq!(feeds[1].xml_url.as_str(), "https://example.net/feed.xml");
+    assert_eq!(feeds[1].title.as_deref(), Some("example"));
+    assert_eq!(
+        feeds[1].html_url.as_ref().map(Url::as_str),
+        Some("https://example.net/")
+    );
+    assert!(parse_opml(b"<rss/>", &url).is_err());
+
+    Ok(())
+}
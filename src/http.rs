@@ -62,6 +62,51 @@ pub async fn get_with_retries<T>(
     }
 }
 
+/// GET request without retries, returning the response if it was successful (http 2xx). this is
+/// for requests that are cheap to give up on, like discovering webmention endpoints.
+pub async fn get_response(client: &Client, url: &str) -> eyre::Result<Response> {
+    let response = get_response_once(client, url).await?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!("GET request failed: http {status:?}: {url}");
+    }
+
+    Ok(response)
+}
+
+/// GET request without retries, returning the body if the response was successful (http 2xx)
+/// and no longer than `max_len` bytes. this is for urls that anyone can give us, like the sources
+/// of received webmentions.
+pub async fn get_bounded(client: &Client, url: &str, max_len: usize) -> eyre::Result<Vec<u8>> {
+    let mut response = get_response(client, url).await?;
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_len as u64)
+    {
+        bail!("GET response too large (over {max_len} bytes): {url}");
+    }
+    let mut body = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_len {
+            bail!("GET response too large (over {max_len} bytes): {url}");
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
+/// POST request with a form body, without retries.
+pub async fn post_form(client: &Client, url: &str, form: &[(&str, &str)]) -> eyre::Result<()> {
+    info!("POST {url}");
+    let status = client.post(url).form(form).send().await?.status();
+    if !status.is_success() {
+        bail!("POST request failed: http {status:?}: {url}");
+    }
+
+    Ok(())
+}
+
 async fn get_response_once(client: &Client, url: &str) -> reqwest::Result<Response> {
     info!("GET {url}");
     client.get(url).send().await
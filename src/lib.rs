@@ -15,10 +15,19 @@ use command::{
     cohost2autost::Cohost2autost,
     cohost2json::Cohost2json,
     cohost_archive::CohostArchive,
+    delete::Delete,
+    export_opml::ExportOpml,
+    fetch::Fetch,
+    hash_password::HashPassword,
     import::{Import, Reimport},
+    import_opml::ImportOpml,
     new::New,
     render::Render,
+    send_webmentions::SendWebmentions,
     server::Server,
+    subscribe::Subscribe,
+    unpublish::Unpublish,
+    unsubscribe::Unsubscribe,
 };
 use dom::{QualNameExt, Transform};
 use html5ever::{Attribute, QualName};
@@ -35,6 +44,7 @@ use crate::{
     meta::extract_metadata,
     path::{PostsPath, SitePath},
     settings::Settings,
+    webmention::{load_webmentions, Webmention, WebmentionKind},
 };
 
 pub mod command {
@@ -42,24 +52,41 @@ pub mod command {
     pub mod cohost2autost;
     pub mod cohost2json;
     pub mod cohost_archive;
+    pub mod delete;
+    pub mod export_opml;
+    pub mod fetch;
+    pub mod hash_password;
     pub mod import;
+    pub mod import_opml;
     pub mod new;
     pub mod render;
+    pub mod send_webmentions;
     pub mod server;
+    pub mod subscribe;
+    pub mod unpublish;
+    pub mod unsubscribe;
 }
 
+pub mod activitypub;
 pub mod akkoma;
 pub mod attachments;
+pub mod auth;
 pub mod cohost;
 pub mod css;
 pub mod dom;
+pub mod feed;
 pub mod http;
 pub mod meta;
+pub mod micropub;
 pub mod migrations;
 pub mod output;
 pub mod path;
+pub mod reader;
 pub mod rocket_eyre;
+pub mod search;
 pub mod settings;
+pub mod watch;
+pub mod webmention;
 
 pub static SETTINGS: LazyLock<Settings> = LazyLock::new(|| {
     #[cfg(test)]
@@ -77,11 +104,20 @@ pub enum Command {
     Cohost2autost(Cohost2autost),
     Cohost2json(Cohost2json),
     CohostArchive(CohostArchive),
+    Delete(Delete),
+    ExportOpml(ExportOpml),
+    Fetch(Fetch),
+    HashPassword(HashPassword),
     Import(Import),
+    ImportOpml(ImportOpml),
     New(New),
     Reimport(Reimport),
     Render(Render),
+    SendWebmentions(SendWebmentions),
     Server(Server),
+    Subscribe(Subscribe),
+    Unpublish(Unpublish),
+    Unsubscribe(Unsubscribe),
 }
 
 /// details about the run, to help with migrations and bug fixes.
@@ -98,19 +134,21 @@ pub struct RunDetailsWriter {
     file: File,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Template)]
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, Template)]
 #[template(path = "post-meta.html")]
 pub struct PostMeta {
     pub archived: Option<String>,
     pub references: Vec<PostsPath>,
     pub title: Option<String>,
     pub published: Option<String>,
+    pub updated: Option<String>,
     pub author: Option<Author>,
     pub tags: Vec<String>,
     pub is_transparent_share: bool,
+    pub is_unpublished: bool,
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Author {
     pub href: String,
     pub name: String,
@@ -126,7 +164,7 @@ pub struct ExtractedPost {
     pub og_description: String,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Thread {
     pub path: Option<PostsPath>,
     pub posts: Vec<TemplatedPost>,
@@ -134,9 +172,12 @@ pub struct Thread {
     pub needs_attachments: BTreeSet<SitePath>,
     pub og_image: Option<String>,
     pub og_description: Option<String>,
+    /// webmentions received for the thread’s own post, oldest first.
+    #[serde(default)]
+    pub webmentions: Vec<Webmention>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TemplatedPost {
     pub path: Option<PostsPath>,
     pub meta: PostMeta,
@@ -276,6 +317,13 @@ impl Thread {
         p.meta.published.cmp(&q.meta.published).reverse()
     }
 
+    pub fn webmentions_of_kind(&self, kind: WebmentionKind) -> Vec<&Webmention> {
+        self.webmentions
+            .iter()
+            .filter(|webmention| webmention.kind == kind)
+            .collect()
+    }
+
     pub fn url_for_original_path(&self) -> eyre::Result<Option<String>> {
         let result = self.path.as_ref().map(|path| path.references_url());
 
@@ -415,6 +463,11 @@ impl TryFrom<TemplatedPost> for Thread {
             .map(|attachment_path| attachment_path.to_owned())
             .collect();
 
+        let webmentions = match &path {
+            Some(path) => load_webmentions(path)?,
+            None => vec![],
+        };
+
         Ok(Thread {
             path,
             posts,
@@ -422,6 +475,7 @@ impl TryFrom<TemplatedPost> for Thread {
             needs_attachments,
             og_image,
             og_description,
+            webmentions,
         })
     }
 }
@@ -473,7 +527,17 @@ impl TemplatedPost {
             .add_tag_attributes("audio", ["controls", "src", "loop"])
             .add_tag_attributes("details", ["open", "name"]) // <details name> for cohost compatibility
             .add_tag_attributes("img", ["loading"])
-            .add_tag_attributes("video", ["controls", "src", "loop"])
+            .add_tag_attributes(
+                "video",
+                [
+                    "controls",
+                    "src",
+                    "loop",
+                    "muted",
+                    "autoplay",
+                    "playsinline",
+                ],
+            )
             .add_tags(["audio", "meta", "video"])
             .add_tag_attributes("meta", ["name", "content"])
             .id_prefix(Some("user-content-")) // cohost compatibility
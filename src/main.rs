@@ -17,10 +17,18 @@ fn main() -> eyre::Result<()> {
         command,
         Command::Attach { .. }
             | Command::Cohost2autost { .. }
+            | Command::Delete { .. }
+            | Command::ExportOpml { .. }
+            | Command::Fetch { .. }
             | Command::Import { .. }
+            | Command::ImportOpml { .. }
             | Command::Reimport { .. }
             | Command::Render { .. }
+            | Command::SendWebmentions { .. }
             | Command::Server { .. }
+            | Command::Subscribe { .. }
+            | Command::Unpublish { .. }
+            | Command::Unsubscribe { .. }
     ) {
         // fail fast if there are any settings errors.
         let _ = &*SETTINGS;
@@ -31,10 +39,19 @@ fn main() -> eyre::Result<()> {
         Command::Cohost2autost(args) => command::cohost2autost::main(args),
         Command::Cohost2json(_) => command::cohost2json::main(),
         Command::CohostArchive(_) => command::cohost_archive::main(),
+        Command::Delete(args) => command::delete::main(args),
+        Command::ExportOpml(args) => command::export_opml::main(args),
+        Command::Fetch(_) => command::fetch::main(),
+        Command::HashPassword(_) => command::hash_password::main(),
         Command::Import(_) => command::import::main(),
+        Command::ImportOpml(args) => command::import_opml::main(args),
         Command::New(args) => command::new::main(args),
         Command::Reimport(_) => command::import::reimport::main(),
         Command::Render(args) => command::render::main(args),
+        Command::SendWebmentions(_) => command::send_webmentions::main(),
         Command::Server(_) => command::server::main(),
+        Command::Subscribe(_) => command::subscribe::main(),
+        Command::Unpublish(args) => command::unpublish::main(args),
+        Command::Unsubscribe(args) => command::unsubscribe::main(args),
     }
 }
@@ -40,6 +40,9 @@ pub fn extract_metadata(unsafe_html: &str) -> eyre::Result<ExtractedPost> {
                         Some("published") => {
                             meta.published = content;
                         }
+                        Some("updated") => {
+                            meta.updated = content;
+                        }
                         Some("author_display_name") => {
                             author_display_name = content;
                         }
@@ -54,6 +57,9 @@ pub fn extract_metadata(unsafe_html: &str) -> eyre::Result<ExtractedPost> {
                         Some("is_transparent_share") => {
                             meta.is_transparent_share = true;
                         }
+                        Some("is_unpublished") => {
+                            meta.is_unpublished = true;
+                        }
                         _ => {}
                     }
                     continue;
@@ -0,0 +1,725 @@
+//! [micropub](https://www.w3.org/TR/micropub/) endpoint for `autost server`, so that micropub
+//! clients can create, update, and delete posts.
+//!
+//! h-entry properties are mapped onto [`PostMeta`] fields like this:
+//! - `name` ↔ `title`
+//! - `category` ↔ `tags`
+//! - `in-reply-to` ↔ `references`, but only for replies to posts on this site
+//! - `published` ↔ `published`
+//! - `updated` ← `updated`
+//! - `content` ↔ the post body, which is markdown for new posts
+//! - `photo` → images at the end of the post body
+//!
+//! deleting a post unpublishes it, so that it can be undeleted.
+
+use std::{collections::BTreeMap, fs::read_to_string, path::Path};
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier as _};
+use askama::Template as _;
+use chrono::{SecondsFormat, Utc};
+use jane_eyre::eyre::{self, eyre, OptionExt as _};
+use rocket::{
+    data::{Data, ToByteUnit as _},
+    form::Form,
+    fs::TempFile,
+    get,
+    http::{uri::Origin, Header, Status},
+    post,
+    request::{FromRequest, Outcome},
+    response::{self, Responder},
+    routes,
+    serde::json::{json, Json, Value},
+    tokio::task::spawn_blocking,
+    FromForm, Request, Response, Route,
+};
+use tracing::{info, warn};
+
+use crate::{
+    command::{render::render_all, server::store_upload, unpublish::unpublish},
+    path::PostsPath,
+    render_markdown,
+    webmention::{send_removal_webmentions_after_render, spawn_send_webmentions},
+    PostMeta, TemplatedPost, Thread, SETTINGS,
+};
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        query_route,
+        json_route,
+        form_route,
+        multipart_route,
+        media_route
+    ]
+}
+
+/// h-entry properties that we understand, from either a form or json request.
+#[derive(Debug, Default, PartialEq)]
+struct Entry {
+    name: Option<String>,
+    /// markdown or html.
+    content: Option<String>,
+    category: Vec<String>,
+    in_reply_to: Vec<String>,
+    published: Option<String>,
+    photo: Vec<Photo>,
+}
+
+#[derive(Debug, PartialEq)]
+struct Photo {
+    url: String,
+    alt: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum MicropubError {
+    Unauthorized(&'static str),
+    InvalidRequest(String),
+    Internal(eyre::Report),
+}
+
+type Result<T> = std::result::Result<T, MicropubError>;
+
+impl<E> From<E> for MicropubError
+where
+    E: Into<eyre::Report>,
+{
+    fn from(error: E) -> Self {
+        Self::Internal(error.into())
+    }
+}
+
+impl<'r> Responder<'r, 'static> for MicropubError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let (status, error, description) = match self {
+            Self::Unauthorized(description) => {
+                (Status::Unauthorized, "unauthorized", description.to_owned())
+            }
+            Self::InvalidRequest(description) => {
+                (Status::BadRequest, "invalid_request", description)
+            }
+            Self::Internal(error) => {
+                warn!("Error: {error:?}");
+                (
+                    Status::InternalServerError,
+                    "internal_error",
+                    format!("{error}"),
+                )
+            }
+        };
+        let body = json!({ "error": error, "error_description": description });
+        Response::build_from(Json(body).respond_to(request)?)
+            .status(status)
+            .ok()
+    }
+}
+
+pub enum MicropubResponse {
+    Created(String),
+    NoContent,
+    Json(Value),
+}
+
+impl<'r> Responder<'r, 'static> for MicropubResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        match self {
+            Self::Created(location) => Response::build()
+                .status(Status::Created)
+                .header(Header::new("Location", location))
+                .ok(),
+            Self::NoContent => Response::build().status(Status::NoContent).ok(),
+            Self::Json(value) => Json(value).respond_to(request),
+        }
+    }
+}
+
+/// the token from the `Authorization: Bearer` header, if any.
+pub struct BearerToken(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BearerToken {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let token = request
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_owned());
+
+        Outcome::Success(Self(token))
+    }
+}
+
+async fn check_token(token: Option<&str>) -> Result<()> {
+    let Some(hash) = SETTINGS.micropub_token_hash.as_deref() else {
+        return Err(MicropubError::Unauthorized(
+            "micropub is disabled, because micropub_token_hash is not set",
+        ));
+    };
+    let Some(token) = token.map(ToOwned::to_owned) else {
+        return Err(MicropubError::Unauthorized("no access token"));
+    };
+    // argon2 is slow on purpose, so keep it off the async runtime.
+    let is_correct = spawn_blocking(move || -> eyre::Result<bool> {
+        let hash = PasswordHash::new(hash).map_err(|error| eyre!("{error}"))?;
+        Ok(Argon2::default()
+            .verify_password(token.as_bytes(), &hash)
+            .is_ok())
+    })
+    .await??;
+    if !is_correct {
+        return Err(MicropubError::Unauthorized("wrong access token"));
+    }
+
+    Ok(())
+}
+
+#[get("/micropub")]
+async fn query_route(token: BearerToken, uri: &Origin<'_>) -> Result<MicropubResponse> {
+    let params = uri
+        .query()
+        .map(|query| form_pairs(query.as_str().as_bytes()))
+        .unwrap_or_default();
+    let param = |key: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    check_token(token.0.as_deref().or(param("access_token"))).await?;
+
+    match param("q") {
+        Some("config") => Ok(MicropubResponse::Json(json!({
+            "media-endpoint": SETTINGS.micropub_media_endpoint(),
+            "syndicate-to": [],
+            "q": ["config", "source", "syndicate-to"],
+        }))),
+        Some("syndicate-to") => Ok(MicropubResponse::Json(json!({ "syndicate-to": [] }))),
+        Some("source") => {
+            let url = param("url").ok_or_else(|| invalid("q=source needs a url"))?;
+            let path = PostsPath::from_rendered_url(url).ok_or_else(|| invalid("no such post"))?;
+            let properties = params
+                .iter()
+                .filter(|(k, _)| k == "properties")
+                .map(|(_, v)| v.as_str())
+                .collect::<Vec<_>>();
+            Ok(MicropubResponse::Json(source(&path, &properties)?))
+        }
+        _ => Err(invalid("unsupported query")),
+    }
+}
+
+#[post("/micropub", format = "json", data = "<body>")]
+async fn json_route(token: BearerToken, body: Json<Value>) -> Result<MicropubResponse> {
+    check_token(token.0.as_deref()).await?;
+    let body = body.into_inner();
+
+    if let Some(action) = body.get("action").and_then(Value::as_str) {
+        let url = body
+            .get("url")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("action needs a url"))?;
+        let path = PostsPath::from_rendered_url(url).ok_or_else(|| invalid("no such post"))?;
+        let action = action.to_owned();
+        // actions render the site, which is slow and blocking.
+        return spawn_blocking(move || match &*action {
+            "update" => {
+                let empty = Default::default();
+                let object = |key| body.get(key).and_then(Value::as_object).unwrap_or(&empty);
+                update(&path, object("replace"), object("add"), body.get("delete"))
+            }
+            "delete" => delete(&path),
+            "undelete" => undelete(&path),
+            _ => Err(invalid("unsupported action")),
+        })
+        .await?;
+    }
+
+    let is_h_entry = body
+        .get("type")
+        .and_then(Value::as_array)
+        .is_some_and(|types| types.iter().any(|t| t == "h-entry"));
+    if !is_h_entry {
+        return Err(invalid("only h-entry is supported"));
+    }
+    let properties = body
+        .get("properties")
+        .and_then(Value::as_object)
+        .ok_or_else(|| invalid("no properties"))?;
+    let entry = Entry::from_json_properties(properties);
+
+    spawn_blocking(move || create(entry)).await?
+}
+
+#[post("/micropub", format = "form", data = "<data>")]
+async fn form_route(token: BearerToken, data: Data<'_>) -> Result<MicropubResponse> {
+    let body = data.open(16.mebibytes()).into_bytes().await?;
+    let pairs = form_pairs(&body);
+    let param = |key: &str| pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    check_token(token.0.as_deref().or(param("access_token").map(|v| &**v))).await?;
+
+    if let Some(action) = param("action") {
+        let url = param("url").ok_or_else(|| invalid("action needs a url"))?;
+        let path = PostsPath::from_rendered_url(url).ok_or_else(|| invalid("no such post"))?;
+        let action = action.clone();
+        return spawn_blocking(move || match &*action {
+            "delete" => delete(&path),
+            "undelete" => undelete(&path),
+            _ => Err(invalid("unsupported action for form requests")),
+        })
+        .await?;
+    }
+    if param("h").is_some_and(|h| h != "entry") {
+        return Err(invalid("only h=entry is supported"));
+    }
+
+    let entry = Entry::from_form_pairs(&pairs);
+    spawn_blocking(move || create(entry)).await?
+}
+
+#[derive(FromForm, Debug)]
+struct MultipartBody<'r> {
+    h: Option<String>,
+    name: Option<String>,
+    content: Option<String>,
+    category: Vec<String>,
+    #[field(name = "in-reply-to")]
+    in_reply_to: Vec<String>,
+    published: Option<String>,
+    photo: Vec<TempFile<'r>>,
+    access_token: Option<String>,
+}
+
+#[post("/micropub", format = "multipart", data = "<body>")]
+async fn multipart_route(
+    token: BearerToken,
+    mut body: Form<MultipartBody<'_>>,
+) -> Result<MicropubResponse> {
+    check_token(token.0.as_deref().or(body.access_token.as_deref())).await?;
+    if body.h.as_deref().is_some_and(|h| h != "entry") {
+        return Err(invalid("only h=entry is supported"));
+    }
+
+    let mut photo = vec![];
+    for file in body.photo.iter_mut() {
+        photo.push(Photo {
+            url: store_upload(file).await?.site_path()?.base_relative_url(),
+            alt: None,
+        });
+    }
+    let body = body.into_inner();
+    let entry = Entry {
+        name: body.name,
+        content: body.content,
+        category: body.category,
+        in_reply_to: body.in_reply_to,
+        published: body.published,
+        photo,
+    };
+
+    spawn_blocking(move || create(entry)).await?
+}
+
+#[derive(FromForm, Debug)]
+struct MediaBody<'r> {
+    file: TempFile<'r>,
+    access_token: Option<String>,
+}
+
+#[post("/micropub/media", format = "multipart", data = "<body>")]
+async fn media_route(
+    token: BearerToken,
+    mut body: Form<MediaBody<'_>>,
+) -> Result<MicropubResponse> {
+    check_token(token.0.as_deref().or(body.access_token.as_deref())).await?;
+    let attachment_path = store_upload(&mut body.file).await?;
+
+    Ok(MicropubResponse::Created(
+        attachment_path.site_path()?.external_url(),
+    ))
+}
+
+impl Entry {
+    fn from_form_pairs(pairs: &[(String, String)]) -> Self {
+        let mut result = Self::default();
+        for (key, value) in pairs {
+            match &**key {
+                "name" => result.name = Some(value.clone()),
+                "content" => result.content = Some(value.clone()),
+                "category" => result.category.push(value.clone()),
+                "in-reply-to" => result.in_reply_to.push(value.clone()),
+                "published" => result.published = Some(value.clone()),
+                "photo" => result.photo.push(Photo {
+                    url: value.clone(),
+                    alt: None,
+                }),
+                _ => {}
+            }
+        }
+
+        result
+    }
+
+    fn from_json_properties(properties: &serde_json::Map<String, Value>) -> Self {
+        let values = |key: &str| {
+            properties
+                .get(key)
+                .and_then(Value::as_array)
+                .map(|values| &values[..])
+                .unwrap_or_default()
+        };
+        let strings = |key: &str| {
+            values(key)
+                .iter()
+                .filter_map(Value::as_str)
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            name: strings("name").into_iter().next(),
+            content: values("content").first().and_then(json_content),
+            category: strings("category"),
+            in_reply_to: strings("in-reply-to"),
+            published: strings("published").into_iter().next(),
+            photo: values("photo")
+                .iter()
+                .filter_map(|photo| match photo {
+                    Value::String(url) => Some(Photo {
+                        url: url.clone(),
+                        alt: None,
+                    }),
+                    Value::Object(photo) => Some(Photo {
+                        url: photo.get("value")?.as_str()?.to_owned(),
+                        alt: photo
+                            .get("alt")
+                            .and_then(Value::as_str)
+                            .map(ToOwned::to_owned),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// the post body: the content, followed by any photos.
+    fn body(&self) -> String {
+        let mut result = self.content.clone().unwrap_or_default();
+        for photo in self.photo.iter() {
+            let alt = photo.alt.as_deref().unwrap_or_default();
+            let alt = alt
+                .replace('\\', "\\\\")
+                .replace('[', "\\[")
+                .replace(']', "\\]");
+            result.push_str(&format!("\n\n![{alt}]({})", local_url(&photo.url)));
+        }
+
+        result
+    }
+}
+
+/// `content` can be plain text, `{"html": ...}`, or `{"value": ...}`.
+fn json_content(content: &Value) -> Option<String> {
+    match content {
+        Value::String(text) => Some(text.clone()),
+        Value::Object(content) => content
+            .get("html")
+            .or_else(|| content.get("value"))
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned),
+        _ => None,
+    }
+}
+
+/// creates a post and renders the site again. rendering blocks, so call this in [`spawn_blocking`].
+fn create(entry: Entry) -> Result<MicropubResponse> {
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let meta = PostMeta {
+        references: references_for_replies(&entry.in_reply_to)?,
+        title: entry.name.clone(),
+        published: Some(entry.published.clone().unwrap_or(now)),
+        author: SETTINGS.self_author.clone(),
+        tags: entry.category.clone(),
+        ..Default::default()
+    };
+    let source = post_source(&meta, &entry.body())?;
+    check_source(&source, true)?;
+
+    let path = PostsPath::create_markdown_post(source.as_bytes())?;
+    info!(?path, "created post via micropub");
+    render_all()?;
+    spawn_send_webmentions(vec![path.clone()]);
+
+    Ok(MicropubResponse::Created(post_url(&path)?))
+}
+
+fn update(
+    path: &PostsPath,
+    replace: &serde_json::Map<String, Value>,
+    add: &serde_json::Map<String, Value>,
+    delete: Option<&Value>,
+) -> Result<MicropubResponse> {
+    rewrite(path, |meta, body| {
+        for (key, values) in replace {
+            let entry =
+                Entry::from_json_properties(&[(key.clone(), values.clone())].into_iter().collect());
+            match &**key {
+                "name" => meta.title = entry.name,
+                "content" => *body = entry.content.unwrap_or_default(),
+                "category" => meta.tags = entry.category,
+                "in-reply-to" => meta.references = references_for_replies(&entry.in_reply_to)?,
+                "published" => meta.published = entry.published,
+                _ => return Err(invalid(&format!("can’t replace property: {key}"))),
+            }
+        }
+        for (key, values) in add {
+            let entry =
+                Entry::from_json_properties(&[(key.clone(), values.clone())].into_iter().collect());
+            match &**key {
+                "category" => {
+                    for tag in entry.category {
+                        if !meta.tags.contains(&tag) {
+                            meta.tags.push(tag);
+                        }
+                    }
+                }
+                "in-reply-to" => meta
+                    .references
+                    .extend(references_for_replies(&entry.in_reply_to)?),
+                "photo" => {
+                    let photos = Entry {
+                        photo: entry.photo,
+                        ..Default::default()
+                    };
+                    body.push_str(&photos.body());
+                }
+                _ => return Err(invalid(&format!("can’t add to property: {key}"))),
+            }
+        }
+        match delete {
+            None => {}
+            Some(Value::Array(keys)) => {
+                for key in keys {
+                    match key.as_str() {
+                        Some("name") => meta.title = None,
+                        Some("category") => meta.tags.clear(),
+                        Some("in-reply-to") => meta.references.clear(),
+                        Some("published") => meta.published = None,
+                        _ => return Err(invalid(&format!("can’t delete property: {key}"))),
+                    }
+                }
+            }
+            Some(Value::Object(values)) => {
+                for (key, values) in values {
+                    let entry = Entry::from_json_properties(
+                        &[(key.clone(), values.clone())].into_iter().collect(),
+                    );
+                    match &**key {
+                        "category" => meta.tags.retain(|tag| !entry.category.contains(tag)),
+                        _ => {
+                            return Err(invalid(&format!("can’t delete values of property: {key}")))
+                        }
+                    }
+                }
+            }
+            Some(_) => return Err(invalid("delete must be an array or object")),
+        }
+        Ok(())
+    })?;
+
+    Ok(MicropubResponse::NoContent)
+}
+
+fn delete(path: &PostsPath) -> Result<MicropubResponse> {
+    let removed_post = unpublish(path)?;
+    render_all()?;
+    send_removal_webmentions_after_render(vec![removed_post])?;
+
+    Ok(MicropubResponse::NoContent)
+}
+
+fn undelete(path: &PostsPath) -> Result<MicropubResponse> {
+    rewrite(path, |meta, _body| {
+        meta.is_unpublished = false;
+        Ok(())
+    })?;
+
+    Ok(MicropubResponse::NoContent)
+}
+
+/// changes the metadata and body of an existing post, and renders the site again.
+fn rewrite(
+    path: &PostsPath,
+    change: impl FnOnce(&mut PostMeta, &mut String) -> Result<()>,
+) -> Result<()> {
+    let source = read_to_string(path)?;
+    let (_, body) = split_post_source(&source);
+    let mut body = body.to_owned();
+    let mut meta = TemplatedPost::load(path)?.meta;
+    change(&mut meta, &mut body)?;
+    meta.updated = Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true));
+
+    let source = post_source(&meta, &body)?;
+    check_source(&source, path.is_markdown_post())?;
+    path.replace_contents(source.as_bytes())?;
+    info!(?path, "updated post via micropub");
+    render_all()?;
+    spawn_send_webmentions(vec![path.clone()]);
+
+    Ok(())
+}
+
+fn source(path: &PostsPath, properties: &[&str]) -> Result<Value> {
+    let source = read_to_string(path)?;
+    let (_, body) = split_post_source(&source);
+    let meta = TemplatedPost::load(path)?.meta;
+
+    let mut result = BTreeMap::<&str, Value>::default();
+    result.insert("name", json!(meta.title.as_slice()));
+    result.insert("content", json!([body.trim()]));
+    result.insert("category", json!(meta.tags));
+    result.insert("published", json!(meta.published.as_slice()));
+    result.insert("updated", json!(meta.updated.as_slice()));
+    let in_reply_to = meta
+        .references
+        .iter()
+        .filter_map(|path| post_url(path).ok())
+        .collect::<Vec<_>>();
+    result.insert("in-reply-to", json!(in_reply_to));
+    result.retain(|key, value| {
+        (properties.is_empty() || properties.contains(key))
+            && value.as_array().is_some_and(|values| !values.is_empty())
+    });
+
+    if properties.is_empty() {
+        Ok(json!({ "type": ["h-entry"], "properties": result }))
+    } else {
+        Ok(json!({ "properties": result }))
+    }
+}
+
+fn post_source(meta: &PostMeta, body: &str) -> eyre::Result<String> {
+    Ok(format!("{}\n\n{}", meta.render()?, body.trim_start()))
+}
+
+/// try rendering the post before writing it, to catch any errors.
+fn check_source(source: &str, is_markdown: bool) -> eyre::Result<()> {
+    let unsafe_html = if is_markdown {
+        render_markdown(source)
+    } else {
+        source.to_owned()
+    };
+    let post = TemplatedPost::filter(&unsafe_html, None)?;
+    let _thread = Thread::try_from(post)?;
+
+    Ok(())
+}
+
+/// splits a post source into the `<meta>` and `<link>` lines at the start, and the body.
+fn split_post_source(source: &str) -> (&str, &str) {
+    let mut header_len = 0;
+    for line in source.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("<meta ") || trimmed.starts_with("<link ") {
+            header_len += line.len();
+        } else {
+            break;
+        }
+    }
+
+    source.split_at(header_len)
+}
+
+/// for replies to posts on this site, returns the references for a reply to that thread.
+fn references_for_replies(urls: &[String]) -> eyre::Result<Vec<PostsPath>> {
+    let mut result = vec![];
+    for url in urls {
+        let Some(path) = PostsPath::from_rendered_url(url) else {
+            info!("ignoring in-reply-to for a post not on this site: {url}");
+            continue;
+        };
+        let post = TemplatedPost::load(&path)?;
+        let thread = Thread::try_from(post)?;
+        result.extend(thread.posts.into_iter().flat_map(|post| post.path));
+    }
+
+    Ok(result)
+}
+
+fn post_url(path: &PostsPath) -> eyre::Result<String> {
+    Ok(path
+        .rendered_path()?
+        .ok_or_eyre("path has no rendered path")?
+        .external_url())
+}
+
+/// makes urls of our own attachments relative, so that render links them into the site.
+fn local_url(url: &str) -> &str {
+    url.strip_prefix(&SETTINGS.external_base_url)
+        .filter(|path| path.starts_with("attachments/"))
+        .filter(|path| urlencoding::decode(path).is_ok_and(|path| Path::new(&*path).is_file()))
+        .unwrap_or(url)
+}
+
+fn form_pairs(body: &[u8]) -> Vec<(String, String)> {
+    url::form_urlencoded::parse(body)
+        .map(|(key, value)| {
+            // `category[]=a&category[]=b` is the same as `category=a&category=b`.
+            let key = key.strip_suffix("[]").unwrap_or(&key).to_owned();
+            (key, value.into_owned())
+        })
+        .collect()
+}
+
+fn invalid(description: &str) -> MicropubError {
+    MicropubError::InvalidRequest(description.to_owned())
+}
+
+#[test]
+fn test_entry_from_form_pairs() {
+    let pairs = form_pairs(
+        b"h=entry&content=hello+world&category[]=a&category[]=b&photo=https://example.net/a.jpg",
+    );
+    assert_eq!(
+        Entry::from_form_pairs(&pairs),
+        Entry {
+            content: Some("hello world".to_owned()),
+            category: vec!["a".to_owned(), "b".to_owned()],
+            photo: vec![Photo {
+                url: "https://example.net/a.jpg".to_owned(),
+                alt: None
+            }],
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_entry_from_json_properties() {
+    let properties = json!({
+        "name": ["title"],
+        "content": [{"html": "<p>hello</p>"}],
+        "category": ["a"],
+        "photo": [{"value": "https://example.net/a.jpg", "alt": "a [cat]"}],
+    });
+    let entry = Entry::from_json_properties(properties.as_object().unwrap());
+    assert_eq!(entry.name.as_deref(), Some("title"));
+    assert_eq!(entry.content.as_deref(), Some("<p>hello</p>"));
+    assert_eq!(entry.category, ["a"]);
+    assert_eq!(
+        entry.body(),
+        "<p>hello</p>\n\n![a \\[cat\\]](https://example.net/a.jpg)"
+    );
+}
+
+#[test]
+fn test_split_post_source() {
+    let source = "<meta name=\"title\" content=\"hi\">\n<link rel=\"author\" href=\"#\">\n\nbody\n<meta name=\"x\">\n";
+    assert_eq!(
+        split_post_source(source),
+        (
+            "<meta name=\"title\" content=\"hi\">\n<link rel=\"author\" href=\"#\">\n\n",
+            "body\n<meta name=\"x\">\n"
+        )
+    );
+}
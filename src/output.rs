@@ -2,8 +2,10 @@
 //! guarantees that path-relative urls are made path-absolute.
 
 use askama::Template;
+use chrono::DateTime;
 use jane_eyre::eyre;
 use markup5ever_rcdom::{NodeData, RcDom};
+use serde::Serialize;
 use tracing::trace;
 
 use crate::{
@@ -14,6 +16,8 @@ use crate::{
         serialize_html_document, serialize_html_fragment, AttrsMutExt, TendrilExt, Transform,
     },
     path::{parse_path_relative_scheme_less_url_string, SitePath},
+    reader::Subscription,
+    webmention::WebmentionKind,
     Author, PostMeta, Thread, SETTINGS,
 };
 
@@ -26,6 +30,21 @@ pub struct ThreadsPageTemplate<'template> {
     threads_content: &'template str,
     page_title: &'template str,
     feed_href: &'template Option<SitePath>,
+    pagination: Option<&'template Pagination>,
+    /// ask search engines not to index this page.
+    noindex: bool,
+}
+
+/// links between the pages of a collection that is split into several pages.
+#[derive(Clone, Debug)]
+pub struct Pagination {
+    /// 1-based
+    pub page: usize,
+    pub page_count: usize,
+    /// newer threads, if this is not the first page.
+    pub prev_href: Option<SitePath>,
+    /// older threads, if this is not the last page.
+    pub next_href: Option<SitePath>,
 }
 
 #[derive(Clone, Debug, Template)]
@@ -35,6 +54,39 @@ pub struct ThreadsContentTemplate<'template> {
     simple_mode: bool,
 }
 
+#[derive(Clone, Debug, Template)]
+#[template(path = "tags.html")]
+pub struct TagsDirectoryTemplate<'template> {
+    /// tags and counts in each group of `interesting_tags`, in the order they were defined.
+    interesting_groups: &'template [Vec<(&'template str, usize)>],
+    /// tags and counts not in `interesting_tags`, most common first.
+    other_tags: &'template [(&'template str, usize)],
+}
+
+#[derive(Clone, Debug, Template)]
+#[template(path = "archive.html")]
+pub struct ArchiveOverviewTemplate<'template> {
+    years: &'template [ArchiveYear<'template>],
+}
+
+/// a year in the archive overview, newest first, with its months, newest first.
+#[derive(Clone, Debug)]
+pub struct ArchiveYear<'period> {
+    pub year: &'period str,
+    pub count: usize,
+    pub months: Vec<(&'period str, usize)>,
+}
+
+#[derive(Clone, Debug, Template)]
+#[template(path = "search.html")]
+pub struct SearchFormTemplate {}
+
+#[derive(Clone, Debug, Template)]
+#[template(path = "blogroll.html")]
+pub struct BlogrollTemplate<'template> {
+    subscriptions: &'template [&'template Subscription],
+}
+
 #[derive(Clone, Debug, Template)]
 #[template(path = "thread-or-post-header.html")]
 pub struct ThreadOrPostHeaderTemplate<'template> {
@@ -61,13 +113,36 @@ pub struct AtomFeedTemplate<'template> {
     cached_threads: Vec<&'template CachedThread>,
     feed_title: &'template str,
     updated: &'template str,
+    links: &'template AtomFeedLinks,
+}
+
+/// links between a feed and its archived feeds, per [RFC 5005](https://www.rfc-editor.org/rfc/rfc5005).
+#[derive(Clone, Debug)]
+pub struct AtomFeedLinks {
+    pub self_href: SitePath,
+    /// the subscription feed, if this is an archived feed.
+    pub current_href: Option<SitePath>,
+    /// the next older archived feed, if any.
+    pub prev_archive_href: Option<SitePath>,
+    /// the next newer archived feed, if any.
+    pub next_archive_href: Option<SitePath>,
 }
 
 impl ThreadsPageTemplate<'_> {
+    /// every atom feed `<stem>.feed.xml` has a json feed `<stem>.feed.json` next to it.
+    fn json_feed_url(&self) -> Option<String> {
+        self.feed_href
+            .as_ref()
+            .map(|href| href.internal_url())
+            .and_then(|url| Some(format!("{}.feed.json", url.strip_suffix(".feed.xml")?)))
+    }
+
     pub fn render(
         threads_content: &str,
         page_title: &str,
         feed_href: &Option<SitePath>,
+        pagination: Option<&Pagination>,
+        noindex: bool,
     ) -> eyre::Result<String> {
         // render the template with a placeholder for `threads_content`, to avoid having to fix relative urls in the
         // html for the same threads over and over (we do that once per thread, when rendering the `CachedThread`).
@@ -76,6 +151,8 @@ impl ThreadsPageTemplate<'_> {
             threads_content: "\u{FDD0}",
             page_title,
             feed_href,
+            pagination,
+            noindex,
         }
         .render()?;
         let result = fix_relative_urls_in_html_document(&template)?;
@@ -102,12 +179,47 @@ impl ThreadsPageTemplate<'_> {
                 threads_content,
                 page_title,
                 feed_href,
+                pagination: None,
+                noindex: false,
+            }
+            .render()?,
+        )
+    }
+}
+
+impl<'template> TagsDirectoryTemplate<'template> {
+    pub fn render(
+        interesting_groups: &'template [Vec<(&'template str, usize)>],
+        other_tags: &'template [(&'template str, usize)],
+    ) -> eyre::Result<String> {
+        fix_relative_urls_in_html_fragment(
+            &Self {
+                interesting_groups,
+                other_tags,
             }
             .render()?,
         )
     }
 }
 
+impl<'template> ArchiveOverviewTemplate<'template> {
+    pub fn render(years: &'template [ArchiveYear<'template>]) -> eyre::Result<String> {
+        fix_relative_urls_in_html_fragment(&Self { years }.render()?)
+    }
+}
+
+impl SearchFormTemplate {
+    pub fn render() -> eyre::Result<String> {
+        fix_relative_urls_in_html_fragment(&Self {}.render()?)
+    }
+}
+
+impl<'template> BlogrollTemplate<'template> {
+    pub fn render(subscriptions: &'template [&'template Subscription]) -> eyre::Result<String> {
+        fix_relative_urls_in_html_fragment(&Self { subscriptions }.render()?)
+    }
+}
+
 impl<'template> ThreadsContentTemplate<'template> {
     pub fn render_normal(thread: &'template Thread) -> eyre::Result<String> {
         fix_relative_urls_in_html_fragment(
@@ -160,35 +272,214 @@ impl<'template> ThreadOrPostMetaTemplate<'template> {
 }
 
 impl<'template> AtomFeedTemplate<'template> {
+    /// the `<updated>` of the feed is the newest time any of the threads were updated, or published
+    /// if never updated. `now` is used only if none of the threads were published.
     pub fn render(
         cached_threads: Vec<&'template CachedThread>,
         feed_title: &'template str,
-        updated: &'template str,
+        now: &'template str,
+        links: &'template AtomFeedLinks,
     ) -> eyre::Result<String> {
+        let updated = latest_timestamp(cached_threads.iter().filter_map(|thread| {
+            let meta = &thread.thread.meta;
+            meta.updated.as_deref().or(meta.published.as_deref())
+        }))
+        .unwrap_or(now);
+
         Ok(Self {
             cached_threads,
             feed_title,
             updated,
+            links,
         }
         .render()?)
     }
 }
 
+/// the latest of the given rfc 3339 timestamps, comparing the times they represent, not the
+/// strings, since timestamps with different utc offsets don’t sort as strings. timestamps that
+/// can’t be parsed are ignored.
+fn latest_timestamp<'a>(timestamps: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    timestamps
+        .filter_map(|timestamp| {
+            DateTime::parse_from_rfc3339(timestamp)
+                .ok()
+                .map(|parsed| (parsed, timestamp))
+        })
+        .max_by_key(|(parsed, _)| *parsed)
+        .map(|(_, timestamp)| timestamp)
+}
+
+#[derive(Clone, Debug, Template)]
+#[template(path = "sitemap.xml")]
+pub struct SitemapTemplate<'template> {
+    entries: &'template [SitemapEntry],
+}
+
+#[derive(Clone, Debug, Template)]
+#[template(path = "sitemap-index.xml")]
+pub struct SitemapIndexTemplate<'template> {
+    entries: &'template [SitemapEntry],
+}
+
+/// [OPML 2.0](https://opml.org/spec2.opml) subscription list.
+#[derive(Clone, Debug, Template)]
+#[template(path = "opml.xml")]
+pub struct OpmlTemplate<'template> {
+    title: &'template str,
+    subscriptions: &'template [&'template Subscription],
+}
+
+#[derive(Clone, Debug)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: Option<String>,
+}
+
+impl<'template> SitemapTemplate<'template> {
+    pub fn render(entries: &'template [SitemapEntry]) -> eyre::Result<String> {
+        Ok(Self { entries }.render()?)
+    }
+}
+
+impl<'template> OpmlTemplate<'template> {
+    pub fn render(
+        title: &'template str,
+        subscriptions: &'template [&'template Subscription],
+    ) -> eyre::Result<String> {
+        Ok(Self {
+            title,
+            subscriptions,
+        }
+        .render()?)
+    }
+}
+
+impl<'template> SitemapIndexTemplate<'template> {
+    pub fn render(entries: &'template [SitemapEntry]) -> eyre::Result<String> {
+        Ok(Self { entries }.render()?)
+    }
+}
+
+/// [JSON Feed 1.1](https://www.jsonfeed.org/version/1.1/), with the same entries as the atom feed.
+#[derive(Debug, Serialize)]
+pub struct JsonFeed<'feed> {
+    version: &'static str,
+    title: &'feed str,
+    home_page_url: String,
+    feed_url: String,
+    items: Vec<JsonFeedItem<'feed>>,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonFeedItem<'feed> {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<&'feed str>,
+    content_html: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_published: Option<&'feed str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    date_modified: Option<&'feed str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    authors: Vec<JsonFeedAuthor<'feed>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    tags: &'feed [String],
+}
+
+#[derive(Debug, Serialize)]
+struct JsonFeedAuthor<'feed> {
+    name: &'feed str,
+    url: &'feed str,
+}
+
+impl<'feed> JsonFeed<'feed> {
+    pub fn render(
+        cached_threads: Vec<&'feed CachedThread>,
+        feed_title: &'feed str,
+        feed_href: &SitePath,
+    ) -> eyre::Result<String> {
+        let items = cached_threads
+            .into_iter()
+            .map(|thread| {
+                let url = thread.thread.url_for_atom_permalink()?;
+                // json feed requires a non-empty id, so fall back to the permalink, and skip threads
+                // that have neither.
+                let Some(id) = thread.thread.atom_feed_entry_id()?.or_else(|| url.clone()) else {
+                    return Ok(None);
+                };
+                let meta = &thread.thread.meta;
+                // json feed has no equivalent of xml:base, and readers ignore base tags in items.
+                let content_html =
+                    absolutise_relative_urls_in_html_fragment(&thread.threads_content_simple)?;
+                // `og_image` was made path-absolute with `base_url`, but readers need an absolute url.
+                let image = thread.thread.og_image.as_deref().map(|url| {
+                    match url.strip_prefix(&SETTINGS.base_url) {
+                        Some(path) => format!("{}{}", SETTINGS.external_base_url, path),
+                        None => url.to_owned(),
+                    }
+                });
+
+                Ok(Some(JsonFeedItem {
+                    id,
+                    url,
+                    title: meta.title.as_deref(),
+                    content_html,
+                    image,
+                    date_published: meta.published.as_deref(),
+                    date_modified: meta.updated.as_deref(),
+                    authors: meta
+                        .author
+                        .iter()
+                        .map(|author| JsonFeedAuthor {
+                            name: &author.name,
+                            url: &author.href,
+                        })
+                        .collect(),
+                    tags: &thread.thread.main_post()?.meta.tags,
+                }))
+            })
+            .filter_map(Result::transpose)
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        Ok(serde_json::to_string_pretty(&JsonFeed {
+            version: "https://jsonfeed.org/version/1.1",
+            title: feed_title,
+            home_page_url: SETTINGS.external_base_url.clone(),
+            feed_url: feed_href.external_url(),
+            items,
+        })?)
+    }
+}
+
 fn fix_relative_urls_in_html_document(html: &str) -> eyre::Result<String> {
     let dom = parse_html_document(html.as_bytes())?;
-    let dom = fix_relative_urls(dom)?;
+    let dom = fix_relative_urls(dom, |url| SETTINGS.base_url_relativise(url))?;
 
     serialize_html_document(dom)
 }
 
 fn fix_relative_urls_in_html_fragment(html: &str) -> eyre::Result<String> {
     let dom = parse_html_fragment(html.as_bytes())?;
-    let dom = fix_relative_urls(dom)?;
+    let dom = fix_relative_urls(dom, |url| SETTINGS.base_url_relativise(url))?;
 
     serialize_html_fragment(dom)
 }
 
-fn fix_relative_urls(dom: RcDom) -> eyre::Result<RcDom> {
+/// makes path-relative urls absolute with `external_base_url`, for html that will be read
+/// somewhere other than the site, where neither `base_url` nor a base tag would help.
+pub fn absolutise_relative_urls_in_html_fragment(html: &str) -> eyre::Result<String> {
+    let dom = parse_html_fragment(html.as_bytes())?;
+    let dom = fix_relative_urls(dom, |url| format!("{}{url}", SETTINGS.external_base_url))?;
+
+    serialize_html_fragment(dom)
+}
+
+fn fix_relative_urls(dom: RcDom, fix_url: impl Fn(&str) -> String) -> eyre::Result<RcDom> {
     let mut transform = Transform::new(dom.document.clone());
     while transform.next(|kids, new_kids| {
         for kid in kids {
@@ -199,7 +490,7 @@ fn fix_relative_urls(dom: RcDom) -> eyre::Result<RcDom> {
                             if let Some(url) =
                                 parse_path_relative_scheme_less_url_string(attr.value.to_str())
                             {
-                                attr.value = SETTINGS.base_url_relativise(&url).into();
+                                attr.value = fix_url(&url).into();
                             }
                         }
                     }
@@ -215,7 +506,7 @@ fn fix_relative_urls(dom: RcDom) -> eyre::Result<RcDom> {
                                 {
                                     trace!(url, "found relative url in inline style");
                                     has_any_relative_urls = true;
-                                    InlineStyleToken::Url(SETTINGS.base_url_relativise(&url))
+                                    InlineStyleToken::Url(fix_url(&url))
                                 } else {
                                     InlineStyleToken::Url(url)
                                 }
@@ -238,3 +529,42 @@ fn fix_relative_urls(dom: RcDom) -> eyre::Result<RcDom> {
 
     Ok(dom)
 }
+
+#[test]
+fn test_latest_timestamp() {
+    // 10:00+10:00 is 00:00Z, which is earlier than 01:00Z, even though it sorts later as a string.
+    let timestamps = ["2024-01-01T10:00:00+10:00", "2024-01-01T01:00:00Z", "bad"];
+    assert_eq!(
+        latest_timestamp(timestamps.into_iter()),
+        Some("2024-01-01T01:00:00Z")
+    );
+    assert_eq!(latest_timestamp(["bad"].into_iter()), None);
+}
+
+#[test]
+fn test_json_feed() -> eyre::Result<()> {
+    use crate::{
+        path::{PostsPath, SITE_PATH_ROOT},
+        TemplatedPost,
+    };
+
+    let path = PostsPath::from_site_root_relative_path("posts/10000000.html")?;
+    let thread = Thread::try_from(TemplatedPost::filter("<p>hi</p>", Some(path))?)?;
+    let cached_thread = CachedThread {
+        thread,
+        threads_content_normal: String::default(),
+        threads_content_simple: r#"<img src="attachments/a.png"><a href="10000001.html">x</a>"#
+            .to_owned(),
+        cache_key: String::default(),
+    };
+    let feed_href = SITE_PATH_ROOT.join("index.feed.json")?;
+    let json = JsonFeed::render(vec![&cached_thread], "feed", &feed_href)?;
+    let json = serde_json::from_str::<serde_json::Value>(&json)?;
+    assert_eq!(json["items"][0]["id"], "10000000.html");
+    assert_eq!(
+        json["items"][0]["content_html"],
+        r#"<img src="https://example.com/attachments/a.png"><a href="https://example.com/10000001.html">x</a>"#
+    );
+
+    Ok(())
+}
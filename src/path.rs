@@ -1,18 +1,23 @@
 use std::{
-    fs::{hard_link, DirEntry},
-    io::ErrorKind,
+    fs::{create_dir_all, hard_link, remove_file, rename, DirEntry, File},
+    io::{ErrorKind, Write},
     path::{Component, Path, PathBuf},
     sync::LazyLock,
 };
 
 use jane_eyre::eyre::{self, bail, Context, OptionExt};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use url::Url;
+use uuid::Uuid;
 
 use crate::SETTINGS;
 
 pub type PostsPath = RelativePath<PostsKind>;
 pub type SitePath = RelativePath<SiteKind>;
 pub type AttachmentsPath = RelativePath<AttachmentsKind>;
+pub type CachePath = RelativePath<CacheKind>;
+pub type WebmentionsPath = RelativePath<WebmentionsKind>;
+pub type ReaderPath = RelativePath<ReaderKind>;
 
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[allow(private_bounds)]
@@ -30,6 +35,7 @@ trait PathKind: Sized {
 pub enum PostsKind {
     Post {
         is_markdown: bool,
+        /// in `posts/imported/` or `posts/reader/`, that is, a copy of a post from elsewhere.
         in_imported_dir: bool,
     },
     Other,
@@ -44,6 +50,15 @@ pub enum SiteKind {
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct AttachmentsKind {}
 
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CacheKind {}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WebmentionsKind {}
+
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ReaderKind {}
+
 impl PathKind for PostsKind {
     const ROOT: &'static str = "posts";
 
@@ -67,7 +82,7 @@ impl PathKind for PostsKind {
                 is_markdown: true,
                 in_imported_dir: false,
             },
-            ["imported", c] if c.ends_with(".html") => Self::Post {
+            ["imported" | "reader", c] if c.ends_with(".html") => Self::Post {
                 is_markdown: false,
                 in_imported_dir: true,
             },
@@ -109,6 +124,13 @@ fn test_posts_kind() -> eyre::Result<()> {
             in_imported_dir: true
         }
     );
+    assert_eq!(
+        PostsKind::new(Path::new("posts/reader/1.html"))?,
+        PostsKind::Post {
+            is_markdown: false,
+            in_imported_dir: true
+        }
+    );
 
     Ok(())
 }
@@ -134,12 +156,54 @@ impl PathKind for AttachmentsKind {
     }
 }
 
+impl PathKind for CacheKind {
+    const ROOT: &'static str = "cache";
+
+    fn new(_path: &Path) -> eyre::Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl PathKind for WebmentionsKind {
+    const ROOT: &'static str = "webmentions";
+
+    fn new(_path: &Path) -> eyre::Result<Self> {
+        Ok(Self {})
+    }
+}
+
+impl PathKind for ReaderKind {
+    const ROOT: &'static str = "reader";
+
+    fn new(_path: &Path) -> eyre::Result<Self> {
+        Ok(Self {})
+    }
+}
+
 impl<Kind: PathKind> AsRef<Path> for RelativePath<Kind> {
     fn as_ref(&self) -> &Path {
         self.inner.as_ref()
     }
 }
 
+impl<Kind: PathKind> Serialize for RelativePath<Kind> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(
+            self.inner
+                .to_str()
+                .expect("guaranteed by RelativePath::new"),
+        )
+    }
+}
+
+impl<'de, Kind: PathKind> Deserialize<'de> for RelativePath<Kind> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+
+        Self::new(path.into()).map_err(D::Error::custom)
+    }
+}
+
 pub static POSTS_PATH_ROOT: LazyLock<PostsPath> =
     LazyLock::new(|| PostsPath::new(PostsKind::ROOT.into()).expect("guaranteed by argument"));
 pub static POSTS_PATH_IMPORTED: LazyLock<PostsPath> = LazyLock::new(|| {
@@ -147,6 +211,11 @@ pub static POSTS_PATH_IMPORTED: LazyLock<PostsPath> = LazyLock::new(|| {
         .join("imported")
         .expect("guaranteed by argument")
 });
+pub static POSTS_PATH_READER: LazyLock<PostsPath> = LazyLock::new(|| {
+    POSTS_PATH_ROOT
+        .join("reader")
+        .expect("guaranteed by argument")
+});
 impl PostsPath {
     /// creates a path from `<link rel=references href>`, which is relative to
     /// the posts directory, but percent-encoded as a url.
@@ -187,6 +256,37 @@ impl PostsPath {
             .expect("guaranteed by argument")
     }
 
+    pub fn reader_post_path(post_id: usize) -> Self {
+        POSTS_PATH_READER
+            .join(&format!("{post_id}.html"))
+            .expect("guaranteed by argument")
+    }
+
+    /// finds the post for a url of its page on the site (or on the server), like
+    /// `https://example.com/1.html`, if that post exists. only posts at the top level of `posts`
+    /// are rendered, so posts in `posts/imported/` and `posts/reader/` are never found.
+    pub fn from_rendered_url(url: &str) -> Option<Self> {
+        let path = match url.strip_prefix(&SETTINGS.external_base_url) {
+            Some(path) => path.to_owned(),
+            None => {
+                let url = Url::parse(url).ok()?;
+                url.path().strip_prefix(&SETTINGS.base_url)?.to_owned()
+            }
+        };
+        let basename = path.strip_suffix(".html")?;
+        if basename.is_empty() || basename.contains('/') {
+            return None;
+        }
+
+        [
+            POSTS_PATH_ROOT.join(&format!("{basename}.md")),
+            POSTS_PATH_ROOT.join(&format!("{basename}.html")),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|path| path.as_ref().is_file())
+    }
+
     pub fn references_url(&self) -> String {
         self.relative_url()
     }
@@ -235,6 +335,58 @@ impl PostsPath {
         )
     }
 
+    /// for posts created by `cohost2autost`, returns the directory containing the posts that this
+    /// post references, like `posts/123456` for `posts/123456.html`.
+    pub fn references_dir_for_post(&self) -> Option<Self> {
+        if !matches!(
+            self.kind,
+            PostsKind::Post {
+                is_markdown: false,
+                in_imported_dir: false,
+            }
+        ) {
+            return None;
+        }
+        let (basename, _) = self.filename().rsplit_once(".")?;
+        let post_id = basename.parse::<usize>().ok()?;
+
+        Some(Self::references_dir(post_id))
+    }
+
+    /// creates a new markdown post with the next available id, returning its path.
+    pub fn create_markdown_post(contents: &[u8]) -> eyre::Result<Self> {
+        // cohost post ids are all less than 10000000.
+        let (mut file, path) = (10000000..)
+            .map(|id| {
+                let path = Self::markdown_post_path(id);
+                File::create_new(&path).map(|file| (file, path))
+            })
+            .find(|file| !matches!(file, Err(error) if error.kind() == ErrorKind::AlreadyExists))
+            .expect("too many posts :(")
+            .wrap_err("failed to create post")?;
+        file.write_all(contents)
+            .wrap_err("failed to write post file")?;
+
+        Ok(path)
+    }
+
+    /// whether this is a post that gets rendered, as opposed to some other file in `posts/`.
+    pub fn is_post(&self) -> bool {
+        matches!(self.kind, PostsKind::Post { .. })
+    }
+
+    /// the file containing the webmentions received for this post, like
+    /// `webmentions/imported/1.html.json` for `posts/imported/1.html`.
+    pub fn webmentions_path(&self) -> eyre::Result<WebmentionsPath> {
+        WEBMENTIONS_PATH_ROOT.join(&format!("{}.json", self.relative_path()))
+    }
+
+    /// the file recording the webmentions sent for this post, like
+    /// `webmentions/sent/imported/1.html.json` for `posts/imported/1.html`.
+    pub fn sent_webmentions_path(&self) -> eyre::Result<WebmentionsPath> {
+        WEBMENTIONS_PATH_SENT.join(&format!("{}.json", self.relative_path()))
+    }
+
     pub fn basename(&self) -> Option<&str> {
         if let PostsKind::Post {
             in_imported_dir: true,
@@ -252,6 +404,22 @@ impl PostsPath {
     }
 }
 
+#[test]
+fn test_references_dir_for_post() -> eyre::Result<()> {
+    let dir = |path: &str| -> eyre::Result<_> {
+        Ok(PostsPath::from_site_root_relative_path(path)?.references_dir_for_post())
+    };
+    assert_eq!(
+        dir("posts/123456.html")?,
+        Some(PostsPath::references_dir(123456))
+    );
+    assert_eq!(dir("posts/123456.md")?, None);
+    assert_eq!(dir("posts/imported/1.html")?, None);
+    assert_eq!(dir("posts/foo.html")?, None);
+
+    Ok(())
+}
+
 pub static SITE_PATH_ROOT: LazyLock<SitePath> =
     LazyLock::new(|| SitePath::new(SiteKind::ROOT.into()).expect("guaranteed by argument"));
 pub static SITE_PATH_TAGGED: LazyLock<SitePath> = LazyLock::new(|| {
@@ -259,6 +427,26 @@ pub static SITE_PATH_TAGGED: LazyLock<SitePath> = LazyLock::new(|| {
         .join("tagged")
         .expect("guaranteed by argument")
 });
+pub static SITE_PATH_ARCHIVE: LazyLock<SitePath> = LazyLock::new(|| {
+    SITE_PATH_ROOT
+        .join("archive")
+        .expect("guaranteed by argument")
+});
+pub static SITE_PATH_SEARCH: LazyLock<SitePath> = LazyLock::new(|| {
+    SITE_PATH_ROOT
+        .join("search")
+        .expect("guaranteed by argument")
+});
+pub static SITE_PATH_ACTIVITYPUB: LazyLock<SitePath> = LazyLock::new(|| {
+    SITE_PATH_ROOT
+        .join("activitypub")
+        .expect("guaranteed by argument")
+});
+pub static SITE_PATH_WELL_KNOWN: LazyLock<SitePath> = LazyLock::new(|| {
+    SITE_PATH_ROOT
+        .join(".well-known")
+        .expect("guaranteed by argument")
+});
 pub static SITE_PATH_ATTACHMENTS: LazyLock<SitePath> = LazyLock::new(|| {
     SITE_PATH_ROOT
         .join("attachments")
@@ -345,6 +533,27 @@ pub static ATTACHMENTS_PATH_COHOST_HEADER: LazyLock<AttachmentsPath> = LazyLock:
         .join("cohost-header")
         .expect("guaranteed by argument")
 });
+#[test]
+fn test_webmentions_path() -> eyre::Result<()> {
+    let path = |path: &str| -> eyre::Result<_> {
+        PostsPath::from_site_root_relative_path(path)?.webmentions_path()
+    };
+    assert_eq!(
+        path("posts/10000000.md")?,
+        WebmentionsPath::from_site_root_relative_path("webmentions/10000000.md.json")?
+    );
+    assert_eq!(
+        path("posts/imported/1.html")?,
+        WebmentionsPath::from_site_root_relative_path("webmentions/imported/1.html.json")?
+    );
+    assert_eq!(
+        PostsPath::from_site_root_relative_path("posts/10000000.md")?.sent_webmentions_path()?,
+        WebmentionsPath::from_site_root_relative_path("webmentions/sent/10000000.md.json")?
+    );
+
+    Ok(())
+}
+
 impl AttachmentsPath {
     pub fn site_path(&self) -> eyre::Result<SitePath> {
         let mut result = SITE_PATH_ATTACHMENTS.to_owned();
@@ -356,6 +565,31 @@ impl AttachmentsPath {
     }
 }
 
+pub static CACHE_PATH_ROOT: LazyLock<CachePath> =
+    LazyLock::new(|| CachePath::new(CacheKind::ROOT.into()).expect("guaranteed by argument"));
+pub static CACHE_PATH_RENDER: LazyLock<CachePath> = LazyLock::new(|| {
+    CACHE_PATH_ROOT
+        .join("render.json")
+        .expect("guaranteed by argument")
+});
+
+pub static WEBMENTIONS_PATH_ROOT: LazyLock<WebmentionsPath> = LazyLock::new(|| {
+    WebmentionsPath::new(WebmentionsKind::ROOT.into()).expect("guaranteed by argument")
+});
+pub static WEBMENTIONS_PATH_SENT: LazyLock<WebmentionsPath> = LazyLock::new(|| {
+    WEBMENTIONS_PATH_ROOT
+        .join("sent")
+        .expect("guaranteed by argument")
+});
+
+pub static READER_PATH_ROOT: LazyLock<ReaderPath> =
+    LazyLock::new(|| ReaderPath::new(ReaderKind::ROOT.into()).expect("guaranteed by argument"));
+pub static READER_PATH_SUBSCRIPTIONS: LazyLock<ReaderPath> = LazyLock::new(|| {
+    READER_PATH_ROOT
+        .join("subscriptions.json")
+        .expect("guaranteed by argument")
+});
+
 #[allow(private_bounds)]
 impl<Kind: PathKind> RelativePath<Kind> {
     #[tracing::instrument]
@@ -417,6 +651,28 @@ impl<Kind: PathKind> RelativePath<Kind> {
             .expect("guaranteed by RelativePath::new")
     }
 
+    /// replaces the contents of the file, via a temporary file and a rename, so that a failed
+    /// write never leaves the file truncated. the temporary file goes in `cache/`, not next to
+    /// the file, so a crash never leaves a stray file in `posts/` for render to trip over.
+    pub fn replace_contents(&self, contents: &[u8]) -> eyre::Result<()> {
+        create_dir_all(&*CACHE_PATH_ROOT)?;
+        let temp_path =
+            CACHE_PATH_ROOT.join(&format!("{}.{}.tmp", self.filename(), Uuid::new_v4()))?;
+        let result = (|| {
+            let mut file = File::create(&temp_path).wrap_err("failed to create temporary file")?;
+            file.write_all(contents)
+                .wrap_err("failed to write temporary file")?;
+            file.sync_all()?;
+            drop(file);
+            rename(&temp_path, self).wrap_err("failed to replace file")
+        })();
+        if result.is_err() {
+            let _ = remove_file(&temp_path);
+        }
+
+        result
+    }
+
     fn components(&self) -> impl Iterator<Item = &str> {
         self.inner.components().skip(1).map(|c| {
             c.as_os_str()
@@ -0,0 +1,342 @@
+//! the reader: subscriptions to other sites’ feeds, which `autost fetch` turns into posts in
+//! `posts/reader/`, and a timeline of those posts for `autost server`.
+//!
+//! subscriptions are stored in `reader/subscriptions.json`, along with the post for each entry we
+//! have seen, so that fetching again only writes entries that are new or changed. entries whose
+//! posts have been deleted are not written again.
+
+use std::{
+    cmp::Reverse,
+    collections::BTreeMap,
+    fs::{create_dir_all, read, remove_file, File},
+    io::ErrorKind,
+};
+
+use askama_rocket::Template;
+use chrono::{DateTime, NaiveDate, NaiveTime, SecondsFormat, Utc};
+use jane_eyre::eyre::{self, bail, OptionExt as _};
+use reqwest::Client;
+use rocket::{get, routes, tokio::task::spawn_blocking, Route};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::{
+    auth::User,
+    command::import::{imported_post_contents, FetchPostResult},
+    feed::{discover_feed_url, parse_feed, Feed},
+    http::get_with_retries,
+    output::ThreadsContentTemplate,
+    path::{PostsPath, POSTS_PATH_READER, READER_PATH_ROOT, READER_PATH_SUBSCRIPTIONS},
+    rocket_eyre, TemplatedPost, Thread, SETTINGS,
+};
+
+/// number of posts on each page of the timeline, if `threads_page_size` is not set.
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Subscriptions {
+    pub subscriptions: Vec<Subscription>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Subscription {
+    /// the url of the atom feed, rss feed, or html page with h-entries.
+    pub url: String,
+    pub title: Option<String>,
+    /// the page the feed is for, if we know it.
+    #[serde(default)]
+    pub html_url: Option<String>,
+    /// whether to list the subscription in the blogroll (`blogroll` setting).
+    #[serde(default)]
+    pub public: bool,
+    /// when the feed was last fetched successfully.
+    pub fetched: Option<String>,
+    /// the posts for the entries we have seen, by the url of each entry.
+    #[serde(default)]
+    pub entries: BTreeMap<String, ReaderEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReaderEntry {
+    pub path: PostsPath,
+    pub published: Option<String>,
+    pub updated: Option<String>,
+}
+
+impl Subscriptions {
+    pub fn load() -> eyre::Result<Self> {
+        match read(&*READER_PATH_SUBSCRIPTIONS) {
+            Ok(json) => Ok(serde_json::from_slice(&json)?),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(other) => Err(other)?,
+        }
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        create_dir_all(&*READER_PATH_ROOT)?;
+        READER_PATH_SUBSCRIPTIONS.replace_contents(serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    pub fn get(&self, url: &str) -> Option<&Subscription> {
+        self.subscriptions
+            .iter()
+            .find(|subscription| subscription.url == url)
+    }
+}
+
+impl Subscription {
+    pub fn new(url: &Url, feed: &Feed) -> Self {
+        Self {
+            url: url.to_string(),
+            title: feed.title.clone(),
+            html_url: (feed.home_url != *url).then(|| feed.home_url.to_string()),
+            public: false,
+            fetched: None,
+            entries: BTreeMap::default(),
+        }
+    }
+
+    /// name to show for the subscription, falling back to its url.
+    pub fn name(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.url)
+    }
+
+    /// url to link to for the subscription, falling back to the url of the feed.
+    pub fn html_url(&self) -> &str {
+        self.html_url.as_deref().unwrap_or(&self.url)
+    }
+}
+
+/// fetches the given url, and if it is a feed, returns it. if it is an html page with no
+/// h-entries, follows any `<link rel=alternate>` to an atom or rss feed instead.
+pub async fn find_feed(client: &Client, url: &Url) -> eyre::Result<(Url, Feed)> {
+    let body = get_with_retries(client, url.as_str(), Ok).await?;
+    if let Some(feed) = parse_feed(&body, url)?.filter(|feed| !feed.entries.is_empty()) {
+        return Ok((url.clone(), feed));
+    }
+    let Some(feed_url) = discover_feed_url(&body, url)? else {
+        bail!("not an atom feed, rss feed, or page with h-entries, and links to no feeds: {url}");
+    };
+    info!("found feed link: {feed_url}");
+    let body = get_with_retries(client, feed_url.as_str(), Ok).await?;
+    let feed =
+        parse_feed(&body, &feed_url)?.ok_or_eyre(format!("not an atom or rss feed: {feed_url}"))?;
+
+    Ok((feed_url, feed))
+}
+
+/// fetches the subscriptions with the given urls, or all subscriptions if none are given.
+pub async fn fetch(urls: &[String]) -> eyre::Result<()> {
+    let mut subscriptions = Subscriptions::load()?;
+    for url in urls {
+        if subscriptions.get(url).is_none() {
+            bail!("not subscribed to {url}");
+        }
+    }
+
+    let client = Client::new();
+    let mut failures = 0;
+    for subscription in subscriptions.subscriptions.iter_mut() {
+        if !urls.is_empty() && !urls.contains(&subscription.url) {
+            continue;
+        }
+        info!("fetching {}", subscription.url);
+        if let Err(error) = fetch_subscription(&client, subscription).await {
+            error!(
+                ?error,
+                url = subscription.url,
+                "failed to fetch subscription"
+            );
+            failures += 1;
+        }
+    }
+    subscriptions.save()?;
+
+    if failures > 0 {
+        bail!("failed to fetch {failures} subscriptions");
+    }
+
+    Ok(())
+}
+
+async fn fetch_subscription(client: &Client, subscription: &mut Subscription) -> eyre::Result<()> {
+    let url = Url::parse(&subscription.url)?;
+    let body = get_with_retries(client, url.as_str(), Ok).await?;
+    let feed = parse_feed(&body, &url)?
+        .ok_or_eyre("not an atom feed, rss feed, or page with h-entries")?;
+    if feed.title.is_some() {
+        subscription.title = feed.title;
+    }
+    if subscription.html_url.is_none() && feed.home_url != url {
+        subscription.html_url = Some(feed.home_url.to_string());
+    }
+
+    let mut changed_entries = vec![];
+    for entry in feed.entries {
+        let key = entry.url.to_string();
+        let path = match subscription.entries.get(&key) {
+            Some(known)
+                if known.published == entry.meta.published
+                    && known.updated == entry.meta.updated =>
+            {
+                continue
+            }
+            // the post was deleted, so we don’t want it back.
+            Some(known) if !known.path.as_ref().exists() => continue,
+            Some(known) => Some(known.path.clone()),
+            None => None,
+        };
+        changed_entries.push((key, path, entry));
+    }
+
+    // writing posts downloads attachments with blocking requests.
+    let written = spawn_blocking(move || {
+        let mut result = vec![];
+        for (key, path, entry) in changed_entries {
+            match write_entry(path, &entry) {
+                Ok(path) => result.push((
+                    key,
+                    ReaderEntry {
+                        path,
+                        published: entry.meta.published,
+                        updated: entry.meta.updated,
+                    },
+                )),
+                Err(error) => warn!(?error, url = key, "failed to write entry"),
+            }
+        }
+        result
+    })
+    .await?;
+    info!(
+        "wrote {} new or changed entries from {}",
+        written.len(),
+        subscription.url
+    );
+    subscription.entries.extend(written);
+    subscription.fetched = Some(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true));
+
+    Ok(())
+}
+
+/// writes the entry to the given post, or to a new post in `posts/reader/`.
+fn write_entry(path: Option<PostsPath>, entry: &FetchPostResult) -> eyre::Result<PostsPath> {
+    let (path, is_new) = match path {
+        Some(path) => (path, false),
+        None => (create_reader_post()?, true),
+    };
+    let result =
+        imported_post_contents(entry.meta.clone(), &entry.content, &entry.base_href, &path)
+            .and_then(|contents| path.replace_contents(contents.as_bytes()));
+    // never leave a new post empty, or render would show it as an empty entry.
+    if let Err(error) = result {
+        if is_new {
+            remove_file(&path)?;
+        }
+        return Err(error);
+    }
+
+    Ok(path)
+}
+
+/// creates an empty post in `posts/reader/` with the next available id, returning its path.
+fn create_reader_post() -> eyre::Result<PostsPath> {
+    create_dir_all(&*POSTS_PATH_READER)?;
+    for post_id in 1.. {
+        let path = PostsPath::reader_post_path(post_id);
+        match File::create_new(&path) {
+            Ok(_) => return Ok(path),
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => continue,
+            Err(other) => Err(other)?,
+        }
+    }
+
+    bail!("too many posts :(")
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![reader_route]
+}
+
+#[derive(Template)]
+#[template(path = "reader.html")]
+struct ReaderTemplate {
+    entries: Vec<TimelineEntry>,
+    page: usize,
+    page_count: usize,
+}
+
+struct TimelineEntry {
+    threads_content: String,
+    subscription: String,
+    /// `references_url` of the post, for the compose links.
+    post: String,
+    original_url: Option<String>,
+}
+
+#[get("/reader?<page>")]
+fn reader_route(_user: User, page: Option<usize>) -> rocket_eyre::Result<ReaderTemplate> {
+    let subscriptions = Subscriptions::load()?;
+    let mut entries = subscriptions
+        .subscriptions
+        .iter()
+        .flat_map(|subscription| {
+            subscription
+                .entries
+                .values()
+                .map(move |entry| (subscription, entry))
+        })
+        .filter(|(_, entry)| entry.path.as_ref().exists())
+        .collect::<Vec<_>>();
+    // newest first, with any entries we can’t date at the end.
+    entries.sort_by_key(|(_, entry)| Reverse(published_sort_key(entry.published.as_deref())));
+
+    let page_size = SETTINGS.threads_page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+    let page_count = entries.len().div_ceil(page_size).max(1);
+    let page = page.unwrap_or(1).clamp(1, page_count);
+    let entries = entries
+        .into_iter()
+        .skip((page - 1) * page_size)
+        .take(page_size)
+        .map(|(subscription, entry)| {
+            let post = TemplatedPost::load(&entry.path)?;
+            let original_url = post.meta.archived.clone();
+            let thread = Thread::try_from(post)?;
+            Ok(TimelineEntry {
+                threads_content: ThreadsContentTemplate::render_normal(&thread)?,
+                subscription: subscription.name().to_owned(),
+                post: entry.path.references_url(),
+                original_url,
+            })
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    Ok(ReaderTemplate {
+        entries,
+        page,
+        page_count,
+    })
+}
+
+/// feeds use rfc 3339 dates with all kinds of offsets, and h-entries often have dates only.
+fn published_sort_key(published: Option<&str>) -> Option<DateTime<Utc>> {
+    let published = published?;
+    if let Ok(published) = DateTime::parse_from_rfc3339(published) {
+        return Some(published.to_utc());
+    }
+    let date = NaiveDate::parse_from_str(published.get(..10)?, "%Y-%m-%d").ok()?;
+
+    Some(date.and_time(NaiveTime::MIN).and_utc())
+}
+
+#[test]
+fn test_published_sort_key() {
+    assert!(
+        published_sort_key(Some("2024-01-01T12:00:00+10:00"))
+            < published_sort_key(Some("2024-01-01T11:30:00+09:00"))
+    );
+    assert!(published_sort_key(Some("2024-01-01")) < published_sort_key(Some("2024-01-02T00:00Z")));
+    assert!(published_sort_key(None) < published_sort_key(Some("2024-01-01")));
+    assert_eq!(published_sort_key(Some("yesterday")), None);
+}
@@ -15,6 +15,8 @@ pub type Result<T, E = EyreReport> = std::result::Result<T, E>;
 #[derive(Debug)]
 pub enum EyreReport {
     BadRequest(eyre::Report),
+    Forbidden(eyre::Report),
+    TooManyRequests(eyre::Report),
     InternalServerError(eyre::Report),
 }
 
@@ -32,6 +34,8 @@ impl<'r> Responder<'r, 'static> for EyreReport {
     fn respond_to(self, request: &Request<'_>) -> response::Result<'static> {
         let (status, error) = match self {
             Self::BadRequest(e) => (Status::BadRequest, e),
+            Self::Forbidden(e) => (Status::Forbidden, e),
+            Self::TooManyRequests(e) => (Status::TooManyRequests, e),
             Self::InternalServerError(e) => (Status::InternalServerError, e),
         };
 
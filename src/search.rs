@@ -0,0 +1,152 @@
+//! static full-text search index, queried by script.js on search.html without a server.
+//!
+//! the index is split into several files, so that searching a large site only needs to fetch the
+//! parts of the index for the terms being searched, and the documents being shown:
+//!
+//! - `search/meta.json` has the number of documents and the number of files of each kind
+//! - `search/terms.<shard>.json` maps terms to the ids of the documents containing them, where
+//!   `<shard>` is the [`term_shard`] of the term
+//! - `search/docs.<chunk>.json` has the details of the documents with ids in that chunk
+//!
+//! document ids are assigned newest first, so sorting results by id sorts them newest first.
+
+use std::collections::BTreeMap;
+
+use jane_eyre::eyre;
+use serde::Serialize;
+
+use crate::Thread;
+
+/// maximum number of documents in each `search/docs.<chunk>.json`.
+const DOCS_CHUNK_SIZE: usize = 500;
+
+/// rough number of postings (term–document pairs) in each `search/terms.<shard>.json`.
+const POSTINGS_PER_TERMS_SHARD: usize = 20_000;
+
+/// terms longer than this (in chars) are probably urls or other junk, so we don’t index them.
+const MAX_TERM_LENGTH: usize = 64;
+
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    docs: Vec<SearchDoc>,
+    terms: BTreeMap<String, Vec<usize>>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchDoc {
+    url: String,
+    title: Option<String>,
+    author: Option<String>,
+    published: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct SearchMeta {
+    doc_count: usize,
+    docs_chunk_size: usize,
+    terms_shard_count: usize,
+}
+
+impl SearchIndex {
+    /// adds a thread to the index, with the given rendered url. threads must be added newest first.
+    pub fn push(&mut self, url: String, thread: &Thread) {
+        let id = self.docs.len();
+        let mut text = vec![];
+        text.extend(thread.meta.title.as_deref());
+        for post in thread.posts.iter() {
+            text.extend(post.meta.title.as_deref());
+            text.extend(post.meta.tags.iter().map(|tag| &**tag));
+            if let Some(author) = post.meta.author.as_ref() {
+                text.push(&author.display_handle);
+                text.push(&author.display_name);
+            }
+            // `og_description` is the text content from `dom::text_content_for_summaries`.
+            text.push(&post.og_description);
+        }
+        for term in text.into_iter().flat_map(tokenize) {
+            let ids = self.terms.entry(term).or_default();
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+
+        self.docs.push(SearchDoc {
+            url,
+            title: thread.meta.title.clone(),
+            author: thread
+                .main_post()
+                .ok()
+                .and_then(|post| post.meta.author.as_ref())
+                .map(|author| author.display_handle.clone()),
+            published: thread.meta.published.clone(),
+        });
+    }
+
+    /// returns the filenames (relative to the `search` directory) and contents of the index.
+    pub fn files(&self) -> eyre::Result<Vec<(String, String)>> {
+        let postings_count = self.terms.values().map(|ids| ids.len()).sum::<usize>();
+        let terms_shard_count = postings_count
+            .div_ceil(POSTINGS_PER_TERMS_SHARD)
+            .next_power_of_two();
+        let mut shards = vec![BTreeMap::<&str, &[usize]>::default(); terms_shard_count];
+        for (term, ids) in self.terms.iter() {
+            shards[term_shard(term, terms_shard_count)].insert(term, ids);
+        }
+
+        let mut result = vec![(
+            "meta.json".to_owned(),
+            serde_json::to_string(&SearchMeta {
+                doc_count: self.docs.len(),
+                docs_chunk_size: DOCS_CHUNK_SIZE,
+                terms_shard_count,
+            })?,
+        )];
+        for (i, shard) in shards.iter().enumerate() {
+            result.push((format!("terms.{i}.json"), serde_json::to_string(shard)?));
+        }
+        for (i, chunk) in self.docs.chunks(DOCS_CHUNK_SIZE).enumerate() {
+            result.push((format!("docs.{i}.json"), serde_json::to_string(chunk)?));
+        }
+
+        Ok(result)
+    }
+}
+
+/// splits text into lowercase terms. this must match `tokenize()` in script.js.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty() && term.chars().count() <= MAX_TERM_LENGTH)
+        .map(|term| term.to_owned())
+        .collect()
+}
+
+/// 32-bit FNV-1a hash of the term, modulo the number of shards. this must match `termShard()` in
+/// script.js.
+pub fn term_shard(term: &str, shard_count: usize) -> usize {
+    let mut hash = 0x811c9dc5u32;
+    for byte in term.bytes() {
+        hash ^= u32::from(byte);
+        hash = hash.wrapping_mul(0x01000193);
+    }
+
+    hash as usize % shard_count
+}
+
+#[test]
+fn test_tokenize() {
+    assert_eq!(
+        tokenize("Hello, wörld! #cohost2autost x-y"),
+        ["hello", "wörld", "cohost2autost", "x", "y"]
+    );
+    assert!(tokenize(&"a".repeat(65)).is_empty());
+}
+
+#[test]
+fn test_term_shard() {
+    // FNV-1a test vectors from <http://www.isthe.com/chongo/src/fnv/test_fnv.c>
+    assert_eq!(term_shard("", usize::MAX), 0x811c9dc5);
+    assert_eq!(term_shard("a", usize::MAX), 0xe40c292c);
+    assert_eq!(term_shard("foobar", usize::MAX), 0xbf9cf968);
+    assert_eq!(term_shard("a", 16), 0xe40c292c % 16);
+}
@@ -2,26 +2,49 @@ use std::{
     collections::{BTreeSet, HashMap},
     fs::File,
     io::{BufRead, BufReader, Read},
+    net::{IpAddr, Ipv6Addr},
     path::{Path, PathBuf},
 };
 
+use argon2::PasswordHash;
 use jane_eyre::eyre::{self, bail};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing::warn;
 
-use crate::{path::parse_path_relative_scheme_less_url_string, Author, TemplatedPost, Thread};
+use crate::{
+    path::parse_path_relative_scheme_less_url_string, Author, PostMeta, TemplatedPost, Thread,
+};
 
-#[derive(Deserialize)]
+/// settings are only serialized for the render cache key, so settings that never affect the
+/// rendered site, including secrets, are skipped.
+#[derive(Deserialize, Serialize)]
 pub struct Settings {
     pub base_url: String,
     pub external_base_url: String,
+    #[serde(skip_serializing)]
     pub server_port: Option<u16>,
+    #[serde(skip_serializing)]
+    pub server_address: Option<IpAddr>,
+    #[serde(skip_serializing)]
+    pub server_password_hash: Option<String>,
+    #[serde(skip_serializing)]
+    pub micropub_token_hash: Option<String>,
+    #[serde(skip_serializing)]
+    micropub_media_endpoint: Option<String>,
+    pub webmention_endpoint: Option<String>,
+    #[serde(skip_serializing)]
+    pub send_webmentions: Option<bool>,
     pub site_title: String,
     pub other_self_authors: Vec<String>,
     pub interesting_tags: Vec<Vec<String>>,
+    pub tag_pages_for_all_tags: Option<bool>,
     archived_thread_tags_path: Option<String>,
     pub archived_thread_tags: Option<HashMap<String, Vec<String>>>,
     pub interesting_output_filenames_list_path: Option<String>,
+    pub threads_page_size: Option<usize>,
+    pub atom_feed_max_entries: Option<usize>,
+    pub noindex_uninteresting_collections: Option<bool>,
+    pub blogroll: Option<bool>,
     interesting_archived_threads_list_path: Option<String>,
     interesting_archived_threads_list: Option<Vec<String>>,
     excluded_archived_threads_list_path: Option<String>,
@@ -30,6 +53,7 @@ pub struct Settings {
     pub renamed_tags: Option<HashMap<String, String>>,
     pub implied_tags: Option<HashMap<String, Vec<String>>>,
     pub nav: Vec<NavLink>,
+    pub collections: Option<Vec<CollectionSettings>>,
 
     #[deprecated(since = "0.3.0", note = "use path_to_static")]
     path_to_autost: Option<String>,
@@ -42,12 +66,44 @@ pub struct TagDefinition {
     pub implies: Option<Vec<String>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct NavLink {
     pub href: String,
     pub text: String,
 }
 
+#[derive(Deserialize, Serialize)]
+pub struct CollectionSettings {
+    pub title: String,
+    /// filename of the collection page, without `.html`.
+    pub filename: String,
+    pub feed: Option<bool>,
+    #[serde(default)]
+    pub filter: CollectionFilter,
+}
+
+/// rules for which threads go in a collection. threads must match all of the rules that are set.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CollectionFilter {
+    /// thread must have all of these tags.
+    pub tags_all: Option<Vec<String>>,
+    /// thread must have at least one of these tags.
+    pub tags_any: Option<Vec<String>>,
+    /// thread must have none of these tags.
+    pub tags_none: Option<Vec<String>>,
+    pub author_href: Option<String>,
+    /// whether the thread is by `[self_author]` or `other_self_authors`.
+    pub self_authored: Option<bool>,
+    pub transparent_share: Option<bool>,
+    /// whether the thread was imported, that is, has an `archived` url.
+    pub archived: Option<bool>,
+    pub archived_prefix: Option<String>,
+    /// inclusive lower bound on `published`, compared as text, like `"2023"` or `"2023-06-01"`.
+    pub published_since: Option<String>,
+    /// exclusive upper bound on `published`, compared as text, like `"2024"` or `"2024-06-01"`.
+    pub published_before: Option<String>,
+}
+
 impl Settings {
     pub fn load_default() -> eyre::Result<Self> {
         Self::load("autost.toml")
@@ -103,6 +159,47 @@ impl Settings {
                 .collect::<Result<Vec<_>, _>>()?;
             result.excluded_archived_threads_list = Some(list);
         }
+        for (name, hash) in [
+            ("server_password_hash", &result.server_password_hash),
+            ("micropub_token_hash", &result.micropub_token_hash),
+        ] {
+            if let Some(Err(error)) = hash.as_deref().map(PasswordHash::new) {
+                bail!(
+                    "{name} setting must be a password hash from `autost hash-password`: {error}"
+                );
+            }
+        }
+        if let Some(endpoint) = &result.webmention_endpoint {
+            if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+                bail!("webmention_endpoint setting must be an absolute http or https url!");
+            }
+        }
+        if let Some(endpoint) = &result.micropub_media_endpoint {
+            if !endpoint.starts_with("https://") && !endpoint.starts_with("http://") {
+                bail!("micropub_media_endpoint setting must be an absolute http or https url!");
+            }
+        }
+        if result.threads_page_size == Some(0) {
+            bail!("threads_page_size setting must not be zero!");
+        }
+        if result.atom_feed_max_entries == Some(0) {
+            bail!("atom_feed_max_entries setting must not be zero!");
+        }
+        // the blogroll is linked from the nav, unless you already link to it yourself.
+        if result.blogroll == Some(true)
+            && !result.nav.iter().any(|link| link.href == "blogroll.html")
+        {
+            result.nav.push(NavLink {
+                href: "blogroll.html".to_owned(),
+                text: "blogroll".to_owned(),
+            });
+        }
+        for collection in result.collections() {
+            let filename = &collection.filename;
+            if filename.is_empty() || filename.starts_with(".") || filename.contains(['/', '\\']) {
+                bail!("collection filename must not be empty, start with a dot, or contain slashes: {filename:?}");
+            }
+        }
         #[allow(deprecated)]
         if result.path_to_autost.is_some() {
             warn!("path_to_autost setting is deprecated; use path_to_static instead");
@@ -134,10 +231,34 @@ impl Settings {
         }
     }
 
+    pub fn collections(&self) -> &[CollectionSettings] {
+        self.collections.as_deref().unwrap_or(&[])
+    }
+
+    pub fn noindex_uninteresting_collections(&self) -> bool {
+        self.noindex_uninteresting_collections.unwrap_or(false)
+    }
+
     pub fn server_port(&self) -> u16 {
         self.server_port.unwrap_or(8420)
     }
 
+    pub fn send_webmentions(&self) -> bool {
+        self.send_webmentions.unwrap_or(false)
+    }
+
+    /// the media endpoint that micropub clients are told to upload to. this comes from settings,
+    /// not the request, since clients can send any `Host` or `X-Forwarded-Proto` they like.
+    pub fn micropub_media_endpoint(&self) -> String {
+        self.micropub_media_endpoint
+            .clone()
+            .unwrap_or_else(|| format!("{}micropub/media", self.external_base_url))
+    }
+
+    pub fn server_address(&self) -> IpAddr {
+        self.server_address.unwrap_or(Ipv6Addr::LOCALHOST.into())
+    }
+
     pub fn page_title(&self, title: Option<&str>) -> String {
         match title {
             Some(title) => format!("{} — {}", title, self.site_title),
@@ -162,6 +283,15 @@ impl Settings {
             .any(|interesting_tag| interesting_tag == tag)
     }
 
+    /// whether `tagged/<tag>.html` and its feeds are rendered for the given tag.
+    pub fn tag_has_page(&self, tag: &str) -> bool {
+        self.tag_pages_for_all_tags() || self.tag_is_interesting(tag)
+    }
+
+    pub fn tag_pages_for_all_tags(&self) -> bool {
+        self.tag_pages_for_all_tags.unwrap_or(false)
+    }
+
     pub fn interesting_tags_iter(&self) -> impl Iterator<Item = &str> {
         self.interesting_tags.iter().flatten().map(|tag| &**tag)
     }
@@ -256,6 +386,52 @@ impl Settings {
     }
 }
 
+impl CollectionFilter {
+    pub fn matches(&self, meta: &PostMeta, settings: &Settings) -> bool {
+        let has_tag = |tag: &String| meta.tags.contains(tag);
+        let published = meta.published.as_deref();
+
+        self.tags_all
+            .as_ref()
+            .is_none_or(|tags| tags.iter().all(has_tag))
+            && self
+                .tags_any
+                .as_ref()
+                .is_none_or(|tags| tags.iter().any(has_tag))
+            && self
+                .tags_none
+                .as_ref()
+                .is_none_or(|tags| !tags.iter().any(has_tag))
+            && self.author_href.as_ref().is_none_or(|href| {
+                meta.author
+                    .as_ref()
+                    .is_some_and(|author| author.href == *href)
+            })
+            && self
+                .self_authored
+                .is_none_or(|self_authored| meta.is_any_self_author(settings) == self_authored)
+            && self
+                .transparent_share
+                .is_none_or(|transparent_share| meta.is_transparent_share == transparent_share)
+            && self
+                .archived
+                .is_none_or(|archived| meta.archived.is_some() == archived)
+            && self.archived_prefix.as_ref().is_none_or(|prefix| {
+                meta.archived
+                    .as_ref()
+                    .is_some_and(|archived| archived.starts_with(prefix))
+            })
+            && self
+                .published_since
+                .as_deref()
+                .is_none_or(|since| published.is_some_and(|published| published >= since))
+            && self
+                .published_before
+                .as_deref()
+                .is_none_or(|before| published.is_some_and(|published| published < before))
+    }
+}
+
 #[test]
 fn test_example() -> eyre::Result<()> {
     Settings::load_example()?;
@@ -263,6 +439,20 @@ fn test_example() -> eyre::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_serialize_skips_server_settings() -> eyre::Result<()> {
+    let mut settings = Settings::load_example()?;
+    let before = serde_json::to_string(&settings)?;
+    settings.server_password_hash = Some("$argon2id$secret".to_owned());
+    settings.micropub_token_hash = Some("$argon2id$secret".to_owned());
+    settings.server_port = Some(1);
+    assert_eq!(serde_json::to_string(&settings)?, before);
+    settings.site_title = "changed".to_owned();
+    assert_ne!(serde_json::to_string(&settings)?, before);
+
+    Ok(())
+}
+
 #[test]
 fn test_resolve_tags() -> eyre::Result<()> {
     let mut settings = Settings::load_example()?;
@@ -311,3 +501,53 @@ fn test_base_url_path_components() -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_collection_filter() -> eyre::Result<()> {
+    let settings = Settings::load_example()?;
+    let meta = PostMeta {
+        published: Some("2023-06-01T00:00Z".to_owned()),
+        author: settings.self_author.clone(),
+        tags: vec!["photography".to_owned(), "birds".to_owned()],
+        ..Default::default()
+    };
+    let filter = |filter: CollectionFilter| filter.matches(&meta, &settings);
+
+    assert!(filter(CollectionFilter::default()));
+    assert!(filter(CollectionFilter {
+        tags_all: Some(vec!["photography".to_owned(), "birds".to_owned()]),
+        tags_none: Some(vec!["cats".to_owned()]),
+        self_authored: Some(true),
+        transparent_share: Some(false),
+        archived: Some(false),
+        published_since: Some("2023".to_owned()),
+        published_before: Some("2024".to_owned()),
+        ..Default::default()
+    }));
+    assert!(!filter(CollectionFilter {
+        tags_all: Some(vec!["photography".to_owned(), "cats".to_owned()]),
+        ..Default::default()
+    }));
+    assert!(!filter(CollectionFilter {
+        tags_any: Some(vec!["cats".to_owned()]),
+        ..Default::default()
+    }));
+    assert!(!filter(CollectionFilter {
+        author_href: Some("https://cohost.org/staff".to_owned()),
+        ..Default::default()
+    }));
+    assert!(!filter(CollectionFilter {
+        archived_prefix: Some("https://cohost.org/".to_owned()),
+        ..Default::default()
+    }));
+    assert!(!filter(CollectionFilter {
+        published_since: Some("2023-06-02".to_owned()),
+        ..Default::default()
+    }));
+    assert!(!filter(CollectionFilter {
+        published_before: Some("2023-06-01".to_owned()),
+        ..Default::default()
+    }));
+
+    Ok(())
+}
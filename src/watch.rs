@@ -0,0 +1,146 @@
+//! watching for changes to the site, for `autost render --watch` and `autost server`.
+
+use std::{
+    collections::BTreeSet,
+    fs::create_dir_all,
+    path::{absolute, Path},
+    sync::mpsc::{channel, RecvTimeoutError},
+    time::Duration,
+};
+
+use jane_eyre::eyre::{self, bail};
+use notify::{RecursiveMode, Watcher as _};
+use tracing::{error, info, warn};
+
+use crate::{
+    command::render::render_all,
+    path::{PostsPath, ATTACHMENTS_PATH_ROOT, POSTS_PATH_ROOT},
+    settings::Settings,
+    SETTINGS,
+};
+
+/// how long to wait for more changes before rendering, so that a burst of changes (like an editor
+/// saving a file via a temporary file) only renders once.
+const DEBOUNCE_TIME: Duration = Duration::from_millis(200);
+
+/// watches `posts`, `attachments`, `autost.toml`, and `path_to_static` for changes, and renders
+/// the site after each burst of changes. calls `on_render` after each successful render, with the
+/// posts that changed and still exist.
+///
+/// rendering is incremental, so only the threads affected by the changes get re-rendered. settings
+/// are only loaded once per process, so changes to `autost.toml` restart the process instead.
+///
+/// this never returns, unless the watcher fails.
+pub fn watch_and_render(mut on_render: impl FnMut(&[PostsPath])) -> eyre::Result<()> {
+    create_dir_all(&*POSTS_PATH_ROOT)?;
+    create_dir_all(&*ATTACHMENTS_PATH_ROOT)?;
+    let mut watched_dirs = vec![
+        absolute(&*POSTS_PATH_ROOT)?,
+        absolute(&*ATTACHMENTS_PATH_ROOT)?,
+    ];
+    if let Some(path_to_static) = SETTINGS.path_to_static() {
+        watched_dirs.push(absolute(path_to_static)?);
+    }
+    let settings_path = absolute("autost.toml")?;
+    let posts_dir = absolute(&*POSTS_PATH_ROOT)?;
+
+    let (sender, receiver) = channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    for dir in watched_dirs.iter() {
+        watcher.watch(dir, RecursiveMode::Recursive)?;
+    }
+    // watch the directory containing autost.toml, not the file itself, because many editors save
+    // files by replacing them, which would stop us from seeing any later changes.
+    let site_dir = settings_path.parent().unwrap_or(Path::new("/"));
+    watcher.watch(site_dir, RecursiveMode::NonRecursive)?;
+    info!("watching for changes: {watched_dirs:?} {settings_path:?}");
+
+    loop {
+        let mut events = vec![receiver.recv()?];
+        loop {
+            match receiver.recv_timeout(DEBOUNCE_TIME) {
+                Ok(event) => events.push(event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => bail!("watcher stopped"),
+            }
+        }
+
+        let mut changed_paths = vec![];
+        let mut settings_changed = false;
+        for event in events {
+            let event = match event {
+                Ok(event) => event,
+                Err(error) => {
+                    warn!(?error, "error while watching for changes");
+                    continue;
+                }
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in event.paths {
+                if path == settings_path {
+                    settings_changed = true;
+                } else if watched_dirs.iter().any(|dir| path.starts_with(dir)) {
+                    changed_paths.push(path);
+                }
+            }
+        }
+
+        if settings_changed {
+            restart();
+        }
+        if !changed_paths.is_empty() {
+            info!("files changed, rendering: {changed_paths:?}");
+            let changed_posts = changed_paths
+                .iter()
+                .filter_map(|path| changed_post(&posts_dir, path))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>();
+            match render_all() {
+                Ok(_) => on_render(&changed_posts),
+                Err(error) => error!(?error, "failed to render"),
+            }
+        }
+    }
+}
+
+/// the post at the given absolute path, if it is a post at the top level of `posts` that still
+/// exists.
+fn changed_post(posts_dir: &Path, path: &Path) -> Option<PostsPath> {
+    let relative_path = path.strip_prefix(posts_dir).ok()?;
+    if relative_path.components().count() != 1 {
+        return None;
+    }
+    let post = POSTS_PATH_ROOT.join(relative_path.to_str()?).ok()?;
+
+    (post.is_post() && post.as_ref().is_file()).then_some(post)
+}
+
+/// restarts the process with the same arguments, if the new settings are valid.
+fn restart() {
+    if let Err(error) = Settings::load_default() {
+        error!(
+            ?error,
+            "autost.toml changed, but the new settings are invalid"
+        );
+        return;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        info!("autost.toml changed, restarting");
+        let error = std::env::current_exe()
+            .map(|exe| {
+                std::process::Command::new(exe)
+                    .args(std::env::args_os().skip(1))
+                    .exec()
+            })
+            .unwrap_or_else(|error| error);
+        error!(?error, "failed to restart");
+    }
+    #[cfg(not(unix))]
+    warn!("autost.toml changed, but settings are only loaded on startup; restart autost to apply them");
+}
@@ -0,0 +1,1029 @@
+//! [webmention](https://www.w3.org/TR/webmention/) receiving endpoint for `autost server`, and
+//! storage for the webmentions it accepts, which render shows under each thread. also sends
+//! webmentions for our own posts.
+//!
+//! webmentions are verified in the background, by fetching the source and checking that it links
+//! to the target. accepted webmentions are stored in `webmentions/`, one json file per post (see
+//! [`PostsPath::webmentions_path`]). if a source no longer links to the target, or can no longer
+//! be fetched, any webmention we accepted from it earlier is removed.
+//!
+//! sent webmentions are recorded in `webmentions/sent/` (see
+//! [`PostsPath::sent_webmentions_path`]), along with a hash of the post source, so that we only
+//! send webmentions again when the post changes.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{create_dir_all, read, remove_file, write},
+    io::ErrorKind,
+    net::IpAddr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use chrono::{SecondsFormat, Utc};
+use jane_eyre::eyre::{self, bail, eyre, Context as _, OptionExt as _};
+use markup5ever_rcdom::{Handle, NodeData};
+use reqwest::{header::LINK, redirect::Policy, Client};
+use rocket::{
+    form::Form,
+    post,
+    response::status::Accepted,
+    routes,
+    tokio::{
+        net::lookup_host,
+        runtime::{self, Runtime},
+        sync::Semaphore,
+        task::spawn_blocking,
+    },
+    FromForm, Route,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use tracing::{info, trace, warn};
+use url::{Host, Url};
+
+use crate::{
+    command::{
+        import::{h_entry_author, has_class, mf2_dt, mf2_find, mf2_find_all, mf2_p, mf2_u},
+        render::render_all,
+    },
+    dom::{
+        html_attributes_with_non_embedding_urls, html_attributes_with_urls, parse_html_document,
+        parse_html_fragment, AttrsRefExt, BreadthTraverse, DepthTraverse, QualName, QualNameExt,
+    },
+    http::{get_bounded, get_response, post_form},
+    path::{PostsPath, POSTS_PATH_IMPORTED},
+    rocket_eyre::{self, EyreReport},
+    Author, TemplatedPost, Thread, SETTINGS,
+};
+
+/// sources larger than this many bytes are not fetched in full, and can’t be verified.
+const MAX_SOURCE_BYTES: usize = 1 << 20;
+
+/// replies and mentions longer than this many characters are truncated.
+const MAX_CONTENT_CHARS: usize = 500;
+
+/// only one change to the stored webmentions at a time, since each change rewrites the file.
+static WEBMENTIONS_LOCK: Mutex<()> = Mutex::new(());
+
+/// at most this many received webmentions are verified at once, since anyone can send them.
+const MAX_PENDING_VERIFICATIONS: usize = 16;
+static PENDING_VERIFICATIONS: Semaphore = Semaphore::const_new(MAX_PENDING_VERIFICATIONS);
+
+/// only one send at a time, so that the same webmention is never sent twice at once.
+static SEND_LOCK: rocket::tokio::sync::Mutex<()> = rocket::tokio::sync::Mutex::const_new(());
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Webmention {
+    pub source: String,
+    /// the url of the h-entry in the source, if any, otherwise the source itself.
+    pub url: String,
+    pub kind: WebmentionKind,
+    pub author: Option<Author>,
+    /// text content, for replies and mentions.
+    pub content: Option<String>,
+    pub published: Option<String>,
+    pub received: String,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WebmentionKind {
+    Like,
+    Repost,
+    Reply,
+    Mention,
+}
+
+/// the webmentions sent for a post, so that later sends can skip targets that have not changed.
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct SentWebmentions {
+    targets: BTreeMap<String, SentWebmention>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct SentWebmention {
+    /// sha-256 of the post source, when we last sent a webmention to this target.
+    source_hash: String,
+    /// None if the target had no webmention endpoint.
+    endpoint: Option<String>,
+    checked: String,
+}
+
+#[derive(FromForm)]
+struct WebmentionForm {
+    source: String,
+    target: String,
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![endpoint_route]
+}
+
+#[post("/webmention", data = "<form>")]
+fn endpoint_route(form: Form<WebmentionForm>) -> rocket_eyre::Result<Accepted<&'static str>> {
+    let bad_request = |message: &str| EyreReport::BadRequest(eyre!("{message}"));
+    let parse = |url: &str| {
+        Url::parse(url)
+            .ok()
+            .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+            .ok_or_else(|| bad_request("source and target must be http or https urls"))
+    };
+    let source = parse(&form.source)?;
+    let target = parse(&form.target)?;
+    if source == target {
+        return Err(bad_request("source and target must be different"));
+    }
+    let path = PostsPath::from_rendered_url(target.as_str())
+        .ok_or_else(|| bad_request("target is not a post on this site"))?;
+    if TemplatedPost::load(&path)?.meta.is_unpublished {
+        return Err(bad_request("target is not a post on this site"));
+    }
+
+    let Ok(permit) = PENDING_VERIFICATIONS.try_acquire() else {
+        return Err(EyreReport::TooManyRequests(eyre!(
+            "too many webmentions waiting to be verified, try again later"
+        )));
+    };
+
+    info!(%source, %target, "received webmention, verifying");
+    rocket::tokio::spawn(async move {
+        let result = match public_client(&source).await {
+            Ok(client) => receive(&client, &source, &target, &path).await,
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            warn!(?error, %source, %target, "failed to process webmention");
+        }
+        drop(permit);
+    });
+
+    Ok(Accepted("webmention will be verified and processed"))
+}
+
+/// verifies the webmention, then adds, updates, or removes the stored webmention from its source,
+/// and renders the site if anything changed.
+async fn receive(
+    client: &Client,
+    source: &Url,
+    target: &Url,
+    path: &PostsPath,
+) -> eyre::Result<()> {
+    let webmention = match verify(client, source, target).await {
+        Ok(Some(webmention)) => Some(webmention),
+        Ok(None) => {
+            info!(%source, %target, "source does not link to target");
+            None
+        }
+        Err(error) => {
+            warn!(?error, %source, "failed to fetch source");
+            None
+        }
+    };
+    if update_webmentions(path, source.as_str(), webmention)? {
+        // renders are slow and blocking, and only run one at a time anyway.
+        spawn_blocking(render_all).await??;
+    }
+
+    Ok(())
+}
+
+/// a client for fetching the source of a received webmention, which only connects to public
+/// addresses, so that anyone sending webmentions can’t make the server fetch things from its own
+/// machine or network. the host is resolved once and pinned, and redirects are only followed
+/// within the same host.
+async fn public_client(source: &Url) -> eyre::Result<Client> {
+    let port = source
+        .port_or_known_default()
+        .ok_or_eyre("source has no port")?;
+    let host = source
+        .host_str()
+        .ok_or_eyre("source has no host")?
+        .to_owned();
+    let mut builder = Client::builder().timeout(Duration::from_secs(30));
+    match source.host() {
+        Some(Host::Ipv4(address)) => check_public_address(address.into())?,
+        Some(Host::Ipv6(address)) => check_public_address(address.into())?,
+        Some(Host::Domain(domain)) => {
+            let addresses = lookup_host((domain, port)).await?.collect::<Vec<_>>();
+            for address in &addresses {
+                check_public_address(address.ip())?;
+            }
+            let address = addresses
+                .first()
+                .ok_or_eyre("source host has no addresses")?;
+            builder = builder.resolve(domain, *address);
+        }
+        None => bail!("source has no host"),
+    }
+    let redirect = Policy::custom(move |attempt| {
+        if attempt.previous().len() >= 10 || attempt.url().host_str() != Some(&*host) {
+            attempt.stop()
+        } else {
+            attempt.follow()
+        }
+    });
+
+    Ok(builder.redirect(redirect).build()?)
+}
+
+fn check_public_address(address: IpAddr) -> eyre::Result<()> {
+    if !is_public_address(address) {
+        bail!("source resolves to a non-public address: {address}");
+    }
+
+    Ok(())
+}
+
+/// false for loopback, private, link-local, and other addresses that are not on the internet.
+fn is_public_address(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => {
+            let [a, b, ..] = address.octets();
+            !(address.is_unspecified()
+                || address.is_loopback()
+                || address.is_private()
+                || address.is_link_local()
+                || address.is_broadcast()
+                || address.is_documentation()
+                || address.is_multicast()
+                // 0.0.0.0/8 (this network), 100.64.0.0/10 (shared), 240.0.0.0/4 (reserved)
+                || a == 0
+                || (a == 100 && (b & 0xC0) == 64)
+                || a >= 240)
+        }
+        IpAddr::V6(address) => {
+            if let Some(address) = address.to_ipv4_mapped() {
+                return is_public_address(address.into());
+            }
+            let [a, ..] = address.segments();
+            !(address.is_unspecified()
+                || address.is_loopback()
+                || address.is_multicast()
+                // fc00::/7 (unique local), fe80::/10 (link-local)
+                || (a & 0xFE00) == 0xFC00
+                || (a & 0xFFC0) == 0xFE80)
+        }
+    }
+}
+
+/// fetches the source, and returns the webmention if the source links to the target.
+pub async fn verify(
+    client: &Client,
+    source: &Url,
+    target: &Url,
+) -> eyre::Result<Option<Webmention>> {
+    let body = get_bounded(client, source.as_str(), MAX_SOURCE_BYTES).await?;
+
+    parse_webmention(&body, source, target)
+}
+
+/// parses a fetched source, returning the webmention if the source links to the target.
+pub fn parse_webmention(
+    source_html: &[u8],
+    source: &Url,
+    target: &Url,
+) -> eyre::Result<Option<Webmention>> {
+    let dom = parse_html_document(source_html)?;
+    if !links_to(dom.document.clone(), source, target)? {
+        return Ok(None);
+    }
+    let received = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+
+    let Some(h_entry) = mf2_find(dom.document.clone(), "h-entry") else {
+        return Ok(Some(Webmention {
+            source: source.to_string(),
+            url: source.to_string(),
+            kind: WebmentionKind::Mention,
+            author: None,
+            content: None,
+            published: None,
+            received,
+        }));
+    };
+    let url = mf2_u(h_entry.clone(), "u-url", source)?.unwrap_or(source.clone());
+    let kind = [
+        ("u-like-of", WebmentionKind::Like),
+        ("u-repost-of", WebmentionKind::Repost),
+        ("u-in-reply-to", WebmentionKind::Reply),
+    ]
+    .into_iter()
+    .find_map(|(class, kind)| {
+        property_urls(h_entry.clone(), class, source)
+            .contains(target)
+            .then_some(kind)
+    })
+    .unwrap_or(WebmentionKind::Mention);
+    let content = match kind {
+        WebmentionKind::Like | WebmentionKind::Repost => None,
+        WebmentionKind::Reply | WebmentionKind::Mention => mf2_p(h_entry.clone(), "e-content")?
+            .filter(|content| !content.is_empty())
+            .map(|content| truncate(&content)),
+    };
+
+    Ok(Some(Webmention {
+        source: source.to_string(),
+        url: url.to_string(),
+        kind,
+        author: h_entry_author(h_entry.clone(), source, &url)?,
+        content,
+        // skip rather than reject a .dt-published without [datetime].
+        published: mf2_dt(h_entry, "dt-published").ok().flatten(),
+        received,
+    }))
+}
+
+/// returns the webmentions received for the given post, oldest first.
+pub fn load_webmentions(path: &PostsPath) -> eyre::Result<Vec<Webmention>> {
+    match read(path.webmentions_path()?) {
+        Ok(json) => Ok(serde_json::from_slice(&json)?),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(vec![]),
+        Err(other) => Err(other)?,
+    }
+}
+
+/// replaces or removes the stored webmention from the given source, returning true if the stored
+/// webmentions changed.
+fn update_webmentions(
+    path: &PostsPath,
+    source: &str,
+    webmention: Option<Webmention>,
+) -> eyre::Result<bool> {
+    let _guard = WEBMENTIONS_LOCK
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    let old_webmentions = load_webmentions(path)?;
+    let mut webmentions = old_webmentions.clone();
+    let index = webmentions
+        .iter()
+        .position(|webmention| webmention.source == source);
+    match (index, webmention) {
+        (Some(index), Some(mut webmention)) => {
+            // keep the original time, so that resending an unchanged webmention changes nothing.
+            webmention.received = webmentions[index].received.clone();
+            webmentions[index] = webmention;
+        }
+        (Some(index), None) => {
+            webmentions.remove(index);
+        }
+        (None, Some(webmention)) => webmentions.push(webmention),
+        (None, None) => {}
+    }
+    if webmentions == old_webmentions {
+        return Ok(false);
+    }
+
+    info!(?path, source, "updating webmentions");
+    let webmentions_path = path.webmentions_path()?;
+    if let Some(parent) = webmentions_path.parent() {
+        create_dir_all(parent)?;
+    }
+    write(
+        webmentions_path,
+        serde_json::to_string_pretty(&webmentions)?,
+    )?;
+
+    Ok(true)
+}
+
+impl Webmention {
+    /// name to show for the author, falling back to the host of the url.
+    pub fn author_name(&self) -> &str {
+        if let Some(author) = &self.author {
+            return &author.display_name;
+        }
+
+        self.url
+            .split_once("://")
+            .map_or(&*self.url, |(_, rest)| rest)
+            .split('/')
+            .next()
+            .unwrap_or(&self.url)
+    }
+}
+
+/// returns true if any element in the document has a url attribute that resolves to the target.
+fn links_to(document: Handle, base_href: &Url, target: &Url) -> eyre::Result<bool> {
+    for node in BreadthTraverse::elements(document) {
+        let NodeData::Element { name, attrs, .. } = &node.data else {
+            unreachable!()
+        };
+        let Some(attr_names) = html_attributes_with_urls().get(name) else {
+            continue;
+        };
+        for attr_name in attr_names {
+            if let Some(url) = attrs.borrow().attr_str(&attr_name.local)? {
+                if base_href.join(url).is_ok_and(|url| &url == target) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+/// urls of a u-property that may be a plain link or an embedded h-cite.
+fn property_urls(h_entry: Handle, class: &str, base_href: &Url) -> Vec<Url> {
+    mf2_find_all(h_entry, class)
+        .into_iter()
+        .filter_map(|node| {
+            if has_class(node.clone(), "h-cite").unwrap_or(false) {
+                mf2_u(node, "u-url", base_href).ok().flatten()
+            } else {
+                mf2_u(node, class, base_href).ok().flatten()
+            }
+        })
+        .collect()
+}
+
+fn truncate(content: &str) -> String {
+    match content.char_indices().nth(MAX_CONTENT_CHARS) {
+        Some((index, _)) => format!("{}…", &content[..index]),
+        None => content.to_owned(),
+    }
+}
+
+/// sends webmentions for the given posts, to the targets that are new or changed since the last
+/// time we sent webmentions for each post. with `dry_run`, only logs what would be sent.
+///
+/// only posts without `archived` are considered, since chosts and imported posts are copies of
+/// posts that were published somewhere else.
+pub async fn send_webmentions(post_paths: &[PostsPath], dry_run: bool) -> eyre::Result<()> {
+    let _guard = SEND_LOCK.lock().await;
+    let client = Client::new();
+    for path in post_paths {
+        if let Err(error) = send_webmentions_for_post(&client, path, dry_run).await {
+            warn!(?error, ?path, "failed to send webmentions");
+        }
+    }
+
+    Ok(())
+}
+
+/// sends webmentions for the given posts in the background, if `send_webmentions` is enabled.
+pub fn spawn_send_webmentions(post_paths: Vec<PostsPath>) {
+    if !SETTINGS.send_webmentions() {
+        return;
+    }
+    rocket::tokio::spawn(async move {
+        if let Err(error) = send_webmentions(&post_paths, false).await {
+            warn!(?error, "failed to send webmentions");
+        }
+    });
+}
+
+/// a deleted or unpublished post, and the targets it had sent webmentions to, which each need one
+/// more webmention so they can find out that the post is gone.
+#[derive(Debug)]
+pub struct RemovedPost {
+    source: String,
+    targets: Vec<String>,
+}
+
+/// removes the record of the webmentions sent for a post that is being deleted or unpublished,
+/// returning what we need to tell the targets that the post is gone.
+pub fn take_sent_webmentions(path: &PostsPath) -> eyre::Result<RemovedPost> {
+    let source = path
+        .rendered_path()?
+        .ok_or_eyre("post has no rendered path")?
+        .external_url();
+    let sent_path = path.sent_webmentions_path()?;
+    let sent = match read(&sent_path) {
+        Ok(json) => serde_json::from_slice::<SentWebmentions>(&json)?,
+        Err(error) if error.kind() == ErrorKind::NotFound => SentWebmentions::default(),
+        Err(other) => Err(other)?,
+    };
+    if sent_path.as_ref().is_file() {
+        info!("removing {sent_path:?}");
+        remove_file(&sent_path)?;
+    }
+    let targets = sent
+        .targets
+        .into_iter()
+        .filter(|(_, sent)| sent.endpoint.is_some())
+        .map(|(target, _)| target)
+        .collect();
+
+    Ok(RemovedPost { source, targets })
+}
+
+/// sends webmentions for the given removed posts, which must no longer be in `site/`, so that
+/// targets fetching the source find it gone. with `dry_run`, only logs what would be sent.
+pub async fn send_removal_webmentions(
+    removed_posts: &[RemovedPost],
+    dry_run: bool,
+) -> eyre::Result<()> {
+    let _guard = SEND_LOCK.lock().await;
+    let client = Client::new();
+    for RemovedPost { source, targets } in removed_posts {
+        for target in targets {
+            if let Err(error) = discover_and_send(&client, source, target, dry_run).await {
+                warn!(?error, target, "failed to send webmention");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// sends webmentions for the given removed posts, if `send_webmentions` is enabled, after the
+/// site has been rendered without them. in the server, they are sent in the background; in the
+/// cli, they are sent before returning.
+pub fn send_removal_webmentions_after_render(removed_posts: Vec<RemovedPost>) -> eyre::Result<()> {
+    if !SETTINGS.send_webmentions() {
+        return Ok(());
+    }
+    match runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(async move {
+                if let Err(error) = send_removal_webmentions(&removed_posts, false).await {
+                    warn!(?error, "failed to send webmentions");
+                }
+            });
+        }
+        Err(_) => {
+            Runtime::new()?.block_on(send_removal_webmentions(&removed_posts, false))?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn send_webmentions_for_post(
+    client: &Client,
+    path: &PostsPath,
+    dry_run: bool,
+) -> eyre::Result<()> {
+    let post = TemplatedPost::load(path)?;
+    if post.meta.archived.is_some() {
+        trace!(?path, "not sending webmentions for archived post");
+        return Ok(());
+    }
+    let source = path
+        .rendered_path()?
+        .ok_or_eyre("post has no rendered path")?
+        .external_url();
+    let source_hash = format!("{:x}", Sha256::digest(read(path)?));
+    // unpublishing a post removes all of its links, so the targets can remove their webmentions.
+    let targets = if post.meta.is_unpublished {
+        BTreeSet::default()
+    } else {
+        webmention_targets(&Thread::try_from(post)?)?
+    };
+
+    let sent_path = path.sent_webmentions_path()?;
+    let mut sent = match read(&sent_path) {
+        Ok(json) => serde_json::from_slice::<SentWebmentions>(&json)?,
+        Err(error) if error.kind() == ErrorKind::NotFound => SentWebmentions::default(),
+        Err(other) => Err(other)?,
+    };
+    // targets that the post no longer links to need a webmention too, so they can find out.
+    let removed_targets = sent
+        .targets
+        .keys()
+        .filter(|target| !targets.contains(*target))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut changed = false;
+    for target in targets.iter().chain(removed_targets.iter()) {
+        if sent
+            .targets
+            .get(target)
+            .is_some_and(|sent| sent.source_hash == source_hash)
+        {
+            continue;
+        }
+        let endpoint = match discover_and_send(client, &source, target, dry_run).await {
+            Ok(endpoint) => endpoint,
+            Err(error) => {
+                warn!(?error, target, "failed to send webmention");
+                continue;
+            }
+        };
+        changed = true;
+        if targets.contains(target) {
+            sent.targets.insert(
+                target.clone(),
+                SentWebmention {
+                    source_hash: source_hash.clone(),
+                    endpoint: endpoint.map(|endpoint| endpoint.to_string()),
+                    checked: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                },
+            );
+        } else {
+            sent.targets.remove(target);
+        }
+    }
+
+    if changed && !dry_run {
+        if let Some(parent) = sent_path.parent() {
+            create_dir_all(parent)?;
+        }
+        write(sent_path, serde_json::to_string_pretty(&sent)?)?;
+    }
+
+    Ok(())
+}
+
+/// discovers the webmention endpoint of the target, and sends a webmention to it if it has one,
+/// returning the endpoint.
+async fn discover_and_send(
+    client: &Client,
+    source: &str,
+    target: &str,
+    dry_run: bool,
+) -> eyre::Result<Option<Url>> {
+    let endpoint = discover_endpoint(client, target)
+        .await
+        .wrap_err("failed to discover webmention endpoint")?;
+    match &endpoint {
+        None => info!(target, "target has no webmention endpoint"),
+        Some(endpoint) if dry_run => {
+            info!(source, target, %endpoint, "would send webmention")
+        }
+        Some(endpoint) => {
+            info!(source, target, %endpoint, "sending webmention");
+            send_webmention(client, endpoint, source, target).await?;
+        }
+    }
+
+    Ok(endpoint)
+}
+
+/// the urls that a thread should send webmentions to: absolute links to other sites in its own
+/// post, and the original urls of any imported posts that it replies to.
+fn webmention_targets(thread: &Thread) -> eyre::Result<BTreeSet<String>> {
+    let mut result = BTreeSet::default();
+    let Some((post, references)) = thread.posts.split_last() else {
+        return Ok(result);
+    };
+    for reference in references {
+        let is_imported = reference
+            .path
+            .as_ref()
+            .is_some_and(|path| path.as_ref().starts_with(&*POSTS_PATH_IMPORTED));
+        if let (true, Some(archived)) = (is_imported, &reference.meta.archived) {
+            result.insert(archived.clone());
+        }
+    }
+
+    let dom = parse_html_fragment(post.safe_html.as_bytes())?;
+    for node in BreadthTraverse::elements(dom.document.clone()) {
+        let NodeData::Element { name, attrs, .. } = &node.data else {
+            unreachable!()
+        };
+        let Some(attr_names) = html_attributes_with_non_embedding_urls().get(name) else {
+            continue;
+        };
+        for attr_name in attr_names {
+            // relative urls are links to our own site.
+            let Some(Ok(url)) = attrs.borrow().attr_str(&attr_name.local)?.map(Url::parse) else {
+                continue;
+            };
+            if (url.scheme() == "http" || url.scheme() == "https")
+                && !url.as_str().starts_with(&SETTINGS.external_base_url)
+            {
+                result.insert(url.to_string());
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// finds the webmention endpoint of the target, per
+/// <https://www.w3.org/TR/webmention/#sender-discovers-receiver-webmention-endpoint>.
+async fn discover_endpoint(client: &Client, target: &str) -> eyre::Result<Option<Url>> {
+    let response = get_response(client, target).await?;
+    // relative urls are relative to the final url, after any redirects.
+    let base_href = response.url().clone();
+    for value in response.headers().get_all(LINK) {
+        if let Some(href) = value.to_str().ok().and_then(webmention_link_from_header) {
+            return Ok(Some(base_href.join(href)?));
+        }
+    }
+
+    let dom = parse_html_document(&response.bytes().await?)?;
+    for node in DepthTraverse::elements(dom.document.clone()) {
+        let NodeData::Element { name, attrs, .. } = &node.data else {
+            unreachable!()
+        };
+        if name != &QualName::html("link") && name != &QualName::html("a") {
+            continue;
+        }
+        let attrs = attrs.borrow();
+        let is_webmention = attrs.attr_str("rel")?.is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|rel| rel.eq_ignore_ascii_case("webmention"))
+        });
+        if let (true, Some(href)) = (is_webmention, attrs.attr_str("href")?) {
+            return Ok(Some(base_href.join(href)?));
+        }
+    }
+
+    Ok(None)
+}
+
+/// finds the first url with `rel="webmention"` in an http `Link` header. this assumes that the
+/// urls have no commas, which is true enough for webmention endpoints.
+fn webmention_link_from_header(header: &str) -> Option<&str> {
+    for link in header.split(',') {
+        let Some((url, params)) = link
+            .trim()
+            .strip_prefix('<')
+            .and_then(|link| link.split_once('>'))
+        else {
+            continue;
+        };
+        let is_webmention = params
+            .split(';')
+            .filter_map(|param| param.split_once('='))
+            .filter(|(name, _)| name.trim().eq_ignore_ascii_case("rel"))
+            .flat_map(|(_, value)| value.trim().trim_matches('"').split_ascii_whitespace())
+            .any(|rel| rel.eq_ignore_ascii_case("webmention"));
+        if is_webmention {
+            return Some(url);
+        }
+    }
+
+    None
+}
+
+async fn send_webmention(
+    client: &Client,
+    endpoint: &Url,
+    source: &str,
+    target: &str,
+) -> eyre::Result<()> {
+    post_form(
+        client,
+        endpoint.as_str(),
+        &[("source", source), ("target", target)],
+    )
+    .await
+}
+
+#[test]
+fn test_parse_webmention() -> eyre::Result<()> {
+    let source = Url::parse("https://example.net/notes/1")?;
+    let target = Url::parse("https://example.com/10000000.html")?;
+    let parse = |html: &str| parse_webmention(html.as_bytes(), &source, &target);
+
+    assert_eq!(parse(r#"<a href="https://example.com/">home</a>"#)?, None);
+
+    let mention = parse(r#"<p>see <a href="https://example.com/10000000.html">this</a>"#)?
+        .expect("links to target");
+    assert_eq!(mention.kind, WebmentionKind::Mention);
+    assert_eq!(mention.url, "https://example.net/notes/1");
+    assert_eq!(mention.author_name(), "example.net");
+
+    let reply = parse(
+        r#"<article class="h-entry">
+            <a class="p-author h-card" href="/">Alice</a>
+            <a class="u-in-reply-to" href="https://example.com/10000000.html">re</a>
+            <a class="u-url" href="/notes/1#reply"><time class="dt-published" datetime="2024-01-01T00:00:00Z">now</time></a>
+            <div class="e-content"> <p>hello!</p> </div>
+        </article>"#,
+    )?
+    .expect("links to target");
+    assert_eq!(reply.kind, WebmentionKind::Reply);
+    assert_eq!(reply.url, "https://example.net/notes/1#reply");
+    assert_eq!(reply.author_name(), "Alice");
+    assert_eq!(reply.content.as_deref(), Some("hello!"));
+    assert_eq!(reply.published.as_deref(), Some("2024-01-01T00:00:00Z"));
+
+    let like = parse(
+        r#"<div class="h-entry">
+            <div class="u-like-of h-cite"><a class="u-url" href="https://example.com/10000000.html">post</a></div>
+            <div class="e-content">liked</div>
+        </div>"#,
+    )?
+    .expect("links to target");
+    assert_eq!(like.kind, WebmentionKind::Like);
+    assert_eq!(like.content, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_webmention_targets() -> eyre::Result<()> {
+    let post = TemplatedPost::filter(
+        &format!(
+            r#"<a href="https://example.net/a">a</a> <a href="{}1.html">ours</a>
+            <a href="tagged/x.html">relative</a> <a href="mailto:a@example.net">mail</a>
+            <img src="https://example.net/embedded.png">"#,
+            SETTINGS.external_base_url
+        ),
+        None,
+    )?;
+    assert_eq!(
+        webmention_targets(&Thread::try_from(post)?)?,
+        BTreeSet::from(["https://example.net/a".to_owned()])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_webmention_link_from_header() {
+    assert_eq!(
+        webmention_link_from_header(r#"<https://example.com/wm>; rel="webmention""#),
+        Some("https://example.com/wm")
+    );
+    assert_eq!(
+        webmention_link_from_header(
+            r#"</style.css>; rel=preload, </wm?a=b>; rel="other webmention""#
+        ),
+        Some("/wm?a=b")
+    );
+    assert_eq!(
+        webmention_link_from_header(r#"<https://example.com/>; rel="webmention.org""#),
+        None
+    );
+    assert_eq!(webmention_link_from_header("garbage"), None);
+}
+
+/// a local stand-in for other sites, which serves the given raw responses by request path (or a
+/// 404), and keeps the raw requests it received.
+#[cfg(test)]
+pub(crate) fn stand_in(
+    responses: Vec<(&'static str, String)>,
+) -> eyre::Result<(Url, std::sync::Arc<Mutex<Vec<String>>>)> {
+    use std::io::{Read as _, Write as _};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let base_url = Url::parse(&format!("http://{}/", listener.local_addr()?))?;
+    let requests = std::sync::Arc::new(Mutex::new(vec![]));
+    let result = requests.clone();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            // read the request head, and the body if there is one.
+            let mut request = vec![];
+            let mut buf = [0; 4096];
+            while let Ok(len @ 1..) = stream.read(&mut buf) {
+                request.extend_from_slice(&buf[..len]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let content_length = head
+                        .lines()
+                        .filter_map(|line| line.split_once(':'))
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if body.len() >= content_length {
+                        break;
+                    }
+                }
+            }
+            let request = String::from_utf8_lossy(&request).into_owned();
+            let path = request.split(' ').nth(1).unwrap_or_default().to_owned();
+            requests.lock().unwrap().push(request);
+            let response = responses
+                .iter()
+                .find(|(response_path, _)| *response_path == path)
+                .map_or(
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+                    |(_, response)| response,
+                );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+
+    Ok((base_url, result))
+}
+
+#[cfg(test)]
+pub(crate) fn stand_in_response(status: &str, headers: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    )
+}
+
+#[tokio::test]
+async fn test_verify() -> eyre::Result<()> {
+    let body =
+        r#"<a class="h-entry u-repost-of" href="https://example.com/10000000.html">repost</a>"#;
+    let large_body = format!("{body}{}", " ".repeat(MAX_SOURCE_BYTES));
+    let (base_url, _) = stand_in(vec![
+        (
+            "/note",
+            stand_in_response("200 OK", "Content-Type: text/html\r\n", body),
+        ),
+        (
+            "/large",
+            stand_in_response("200 OK", "Content-Type: text/html\r\n", &large_body),
+        ),
+    ])?;
+    let source = base_url.join("note")?;
+    let target = Url::parse("https://example.com/10000000.html")?;
+
+    let repost = verify(&Client::new(), &source, &target)
+        .await?
+        .expect("links to target");
+    assert_eq!(repost.kind, WebmentionKind::Repost);
+    let other_target = Url::parse("https://example.com/10000001.html")?;
+    assert_eq!(verify(&Client::new(), &source, &other_target).await?, None);
+    let large_source = base_url.join("large")?;
+    assert!(verify(&Client::new(), &large_source, &target)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_discover_and_send() -> eyre::Result<()> {
+    let html = |body| stand_in_response("200 OK", "Content-Type: text/html\r\n", body);
+    let (base_url, requests) = stand_in(vec![
+        (
+            "/header",
+            stand_in_response(
+                "200 OK",
+                "Link: </endpoint?from=header>; rel=\"webmention\"\r\n",
+                r#"<link rel="webmention" href="/endpoint?from=html">"#,
+            ),
+        ),
+        (
+            "/post/html",
+            html(r#"<a href="/">home</a> <link rel="webmention" href="../endpoint?from=html">"#),
+        ),
+        ("/none", html(r#"<a rel="me" href="/">home</a>"#)),
+        (
+            "/endpoint?from=header",
+            stand_in_response("202 Accepted", "", ""),
+        ),
+    ])?;
+    let client = Client::new();
+    let discover =
+        async |path: &str| discover_endpoint(&client, base_url.join(path)?.as_str()).await;
+
+    let endpoint = discover("header").await?.expect("has endpoint");
+    assert_eq!(endpoint, base_url.join("endpoint?from=header")?);
+    assert_eq!(
+        discover("post/html").await?,
+        Some(base_url.join("endpoint?from=html")?)
+    );
+    assert_eq!(discover("none").await?, None);
+    assert!(discover("missing").await.is_err());
+
+    send_webmention(
+        &client,
+        &endpoint,
+        "https://example.com/1.html",
+        "https://example.net/a?b",
+    )
+    .await?;
+    let requests = requests.lock().unwrap();
+    let request = requests.last().expect("sent request");
+    assert!(request.starts_with("POST /endpoint?from=header "));
+    assert!(request.ends_with(
+        "source=https%3A%2F%2Fexample.com%2F1.html&target=https%3A%2F%2Fexample.net%2Fa%3Fb"
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_is_public_address() -> eyre::Result<()> {
+    for address in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+        assert!(is_public_address(address.parse()?), "{address}");
+    }
+    for address in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "::",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public_address(address.parse()?), "{address}");
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_public_client() -> eyre::Result<()> {
+    for source in [
+        "http://127.0.0.1:8420/",
+        "http://[::1]/",
+        "http://localhost/",
+        "http://169.254.169.254/latest/meta-data/",
+    ] {
+        assert!(
+            public_client(&Url::parse(source)?).await.is_err(),
+            "{source}"
+        );
+    }
+
+    Ok(())
+}
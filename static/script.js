@@ -2,7 +2,6 @@ const compose = document.querySelector("form.compose");
 if (compose) {
     const sourceField = compose.querySelector(":scope > textarea.source");
     const previewButton = compose.querySelector(":scope > button.preview");
-    const publishButton = compose.querySelector(":scope > button.publish");
     const submitForm = async action => {
         const data = new URLSearchParams(new FormData(compose));
         const response = await fetch(action, {
@@ -38,9 +37,11 @@ if (compose) {
             error(e);
         }
     };
-    const publish = async () => {
+    const publish = async (button) => {
         try {
-            const response = await submitForm(publishButton.formAction + "?js");
+            const action = new URL(button.formAction);
+            action.searchParams.set("js", "true");
+            const response = await submitForm(action);
             const body = await response.text();
             if (response.ok) {
                 location = body;
@@ -54,9 +55,14 @@ if (compose) {
     };
     compose.addEventListener("submit", event => {
         event.preventDefault();
-        if (event.submitter.value == "publish") {
+        if (event.submitter.value == "publish" || event.submitter.value == "unpublish") {
             event.submitter.disabled = true;
-            publish();
+            publish(event.submitter);
+        } else if (event.submitter.value == "delete") {
+            if (confirm("delete this post? this can’t be undone.")) {
+                event.submitter.disabled = true;
+                publish(event.submitter);
+            }
         } else {
             event.preventDefault();
             preview();
@@ -65,12 +71,147 @@ if (compose) {
     sourceField.addEventListener("input", event => {
         preview();
     });
+
+    // upload files as attachments, and insert links to them at the cursor.
+    const attach = async files => {
+        try {
+            for (const file of files) {
+                const data = new FormData();
+                data.append("file", file);
+                if (compose.elements.csrf) {
+                    data.append("csrf", compose.elements.csrf.value);
+                }
+                const response = await fetch("attach", {
+                    method: "post",
+                    body: data,
+                });
+                const url = await response.text();
+                if (!response.ok) {
+                    throw new Error(url);
+                }
+                const escape = text => text.replace(/[\\\[\]]/g, "\\$&");
+                let markdown;
+                if (file.type.startsWith("image/")) {
+                    const alt = prompt(`alt text for ${file.name}:`) ?? "";
+                    markdown = `![${escape(alt)}](${url})`;
+                } else {
+                    markdown = `[${escape(file.name)}](${url})`;
+                }
+                sourceField.focus();
+                sourceField.setRangeText(markdown, sourceField.selectionStart, sourceField.selectionEnd, "end");
+            }
+            preview();
+        } catch (e) {
+            error(e);
+        }
+    };
+    const attachField = compose.querySelector(":scope > label.attach > input[type=file]");
+    attachField.addEventListener("change", async event => {
+        await attach([...attachField.files]);
+        attachField.value = "";
+    });
+    sourceField.addEventListener("dragover", event => {
+        if (event.dataTransfer.types.includes("Files")) {
+            event.preventDefault();
+        }
+    });
+    sourceField.addEventListener("drop", event => {
+        if (event.dataTransfer.files.length > 0) {
+            event.preventDefault();
+            attach([...event.dataTransfer.files]);
+        }
+    });
+    sourceField.addEventListener("paste", event => {
+        if (event.clipboardData.files.length > 0) {
+            event.preventDefault();
+            attach([...event.clipboardData.files]);
+        }
+    });
     previewButton.style.display = "none";
     addEventListener("DOMContentLoaded", event => {
         preview();
     });
 }
 
+const search = document.querySelector("form.search");
+if (search) {
+    const queryField = search.querySelector(":scope > input[name=q]");
+    const status = search.querySelector(":scope > p.status");
+    const results = search.querySelector(":scope > ol.results");
+    const searchUrl = `${document.body.dataset.baseUrl}search/`;
+    const maxResults = 100;
+    const jsonCache = new Map;
+    const fetchJson = async url => {
+        if (!jsonCache.has(url)) {
+            jsonCache.set(url, fetch(url).then(response => {
+                if (!response.ok) throw new Error(`GET ${url}: ${response.status}`);
+                return response.json();
+            }));
+        }
+        return await jsonCache.get(url);
+    };
+    // must match `search::tokenize()` in autost.
+    const tokenize = text => text.toLowerCase()
+        .split(/[^\p{Alphabetic}\p{N}]+/u)
+        .filter(term => term.length > 0 && [...term].length <= 64);
+    // must match `search::term_shard()` in autost.
+    const termShard = (term, shardCount) => {
+        let hash = 0x811c9dc5;
+        for (const byte of new TextEncoder().encode(term)) {
+            hash ^= byte;
+            hash = Math.imul(hash, 0x01000193);
+        }
+        return (hash >>> 0) % shardCount;
+    };
+    const run = async () => {
+        const terms = [...new Set(tokenize(queryField.value))];
+        results.innerHTML = "";
+        status.textContent = "";
+        if (terms.length == 0) return;
+        status.textContent = "searching…";
+        try {
+            const meta = await fetchJson(`${searchUrl}meta.json`);
+            // find the documents containing every term. ids are sorted newest first.
+            let ids = null;
+            for (const term of terms) {
+                const shard = await fetchJson(`${searchUrl}terms.${termShard(term, meta.terms_shard_count)}.json`);
+                const termIds = new Set(shard[term] ?? []);
+                ids = ids == null ? [...termIds] : ids.filter(id => termIds.has(id));
+            }
+            status.textContent = ids.length == 1 ? "1 post found" : `${ids.length} posts found`;
+            if (ids.length > maxResults) {
+                status.textContent += `, showing the newest ${maxResults}`;
+            }
+            for (const id of ids.slice(0, maxResults)) {
+                const chunk = Math.floor(id / meta.docs_chunk_size);
+                const docs = await fetchJson(`${searchUrl}docs.${chunk}.json`);
+                const doc = docs[id % meta.docs_chunk_size];
+                const li = document.createElement("li");
+                const a = document.createElement("a");
+                a.href = doc.url;
+                a.textContent = doc.title ?? doc.url;
+                li.append(a);
+                const details = [doc.author, doc.published].filter(x => x != null).join(", ");
+                if (details) {
+                    const small = document.createElement("small");
+                    small.textContent = ` — ${details}`;
+                    li.append(small);
+                }
+                results.append(li);
+            }
+        } catch (e) {
+            status.textContent = `${e}`;
+        }
+    };
+    search.addEventListener("submit", event => {
+        event.preventDefault();
+        history.replaceState(null, "", `?${new URLSearchParams({ q: queryField.value })}`);
+        run();
+    });
+    queryField.value = new URLSearchParams(location.search).get("q") ?? "";
+    run();
+}
+
 checkAutostServer();
 
 async function checkAutostServer() {
@@ -88,13 +229,29 @@ async function checkAutostServer() {
     li.append(a);
     navUl.append(li);
 
+    const readerLi = document.createElement("li");
+    const readerA = document.createElement("a");
+    readerA.href = `${document.body.dataset.baseUrl}reader`;
+    readerA.textContent = "reader";
+    readerA.className = "server";
+    readerLi.append(readerA);
+    navUl.append(readerLi);
+
     for (const thread of document.querySelectorAll("article.thread")) {
+        // the reader page has its own reply and rechost links, and its posts are not ours to edit.
+        if (thread.closest("main.reader")) continue;
         const actions = thread.querySelector(":scope > article.post:last-child > footer > .actions");
         const a = document.createElement("a");
         a.href = `${document.body.dataset.baseUrl}compose?${new URLSearchParams({ reply_to: thread.dataset.originalPath })}`;
         a.textContent = "reply";
         a.className = "server";
         actions.prepend(a);
+
+        const edit = document.createElement("a");
+        edit.href = `${document.body.dataset.baseUrl}edit?${new URLSearchParams({ post: thread.dataset.originalPath })}`;
+        edit.textContent = "edit";
+        edit.className = "server";
+        actions.prepend(edit);
     }
 
     for (const tag of document.querySelectorAll("nav .tags > li > .tag, article.post > footer .tags > .tag")) {
@@ -106,6 +263,12 @@ async function checkAutostServer() {
         a.className = "server";
         actions.append(a);
     }
+
+    // reload the page whenever the server renders the site again, unless we might lose a draft.
+    if (!compose) {
+        const events = new EventSource(`${document.body.dataset.baseUrl}events`);
+        events.addEventListener("reload", () => location.reload());
+    }
 }
 
 function renderTerminalError(pre) {
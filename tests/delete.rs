@@ -0,0 +1,49 @@
+use std::{
+    env::{set_current_dir, temp_dir},
+    fs::{copy, create_dir_all, remove_dir_all, write},
+    path::Path,
+};
+
+use autost::{
+    command::{delete::delete, render::render_all},
+    path::PostsPath,
+};
+use jane_eyre::eyre;
+use uuid::Uuid;
+
+/// deleting a post that another post replies to would make that post fail to render, so the
+/// delete must be refused, and the site must still render afterwards.
+#[test]
+fn test_delete_referenced_post() -> eyre::Result<()> {
+    let site_path = temp_dir().join(format!("autost-test-{}", Uuid::new_v4()));
+    create_dir_all(site_path.join("posts"))?;
+    copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("autost.toml.example"),
+        site_path.join("autost.toml"),
+    )?;
+    write(site_path.join("posts/1.md"), "first post\n")?;
+    write(
+        site_path.join("posts/2.md"),
+        "<link rel=\"references\" href=\"1.md\">\n\nreply\n",
+    )?;
+
+    // this is the only test in this binary, so changing the working directory is safe.
+    set_current_dir(&site_path)?;
+    render_all()?;
+    let first = PostsPath::from_site_root_relative_path("posts/1.md")?;
+    let reply = PostsPath::from_site_root_relative_path("posts/2.md")?;
+    let error = delete(&first).expect_err("post has a reply");
+    assert!(error.to_string().contains("posts/2.md"), "{error}");
+    assert!(site_path.join("posts/1.md").is_file());
+    render_all()?;
+
+    // once the reply is gone, the post can be deleted too.
+    delete(&reply)?;
+    delete(&first)?;
+    render_all()?;
+    assert!(!site_path.join("site/1.html").exists());
+    assert!(!site_path.join("site/2.html").exists());
+    remove_dir_all(&site_path)?;
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::{
+    env::{set_current_dir, temp_dir},
+    fs::{copy, create_dir_all, remove_dir_all, write},
+    path::Path,
+};
+
+use autost::command::render::render_all;
+use jane_eyre::eyre;
+use uuid::Uuid;
+
+/// a temporary file left in `posts/` by a crash must not stop the rest of the site from rendering.
+#[test]
+fn test_render_with_stray_temporary_file() -> eyre::Result<()> {
+    let site_path = temp_dir().join(format!("autost-test-{}", Uuid::new_v4()));
+    create_dir_all(site_path.join("posts"))?;
+    copy(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("autost.toml.example"),
+        site_path.join("autost.toml"),
+    )?;
+    write(
+        site_path.join("posts/10000000.md"),
+        "<meta name=\"title\" content=\"hello\">\n\nhello world\n",
+    )?;
+    write(site_path.join("posts/10000000.md.tmp"), "")?;
+
+    // this is the only test in this binary, so changing the working directory is safe.
+    set_current_dir(&site_path)?;
+    render_all()?;
+    assert!(site_path.join("site/10000000.html").is_file());
+    assert!(!site_path.join("site/10000000.md.html").exists());
+    remove_dir_all(&site_path)?;
+
+    Ok(())
+}